@@ -38,6 +38,8 @@
 
             nativeBuildInputs = [ pkgs.pkg-config ];
             buildInputs = pkgs.lib.optionals pkgs.stdenv.isLinux [ pkgs.udev ];
+            # the D-Bus tests start a private dbus-daemon
+            nativeCheckInputs = [ pkgs.dbus ];
 
             meta.mainProgram = "framework-led-widgets";
           };
@@ -56,6 +58,7 @@
             pkgs.rustfmt
             pkgs.clippy
             pkgs.pkg-config
+            pkgs.dbus
           ]
           ++ pkgs.lib.optionals pkgs.stdenv.isLinux [ pkgs.udev ];
         };
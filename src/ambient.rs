@@ -0,0 +1,176 @@
+use std::path::PathBuf;
+
+use crate::config::{AutoBrightnessConfig, BrightnessPoint};
+
+/// Panel brightness driven by an IIO ambient light sensor.
+///
+/// The sensor is read through sysfs, so any regular file containing a number
+/// works as a stand-in for the real device.
+pub struct AutoBrightness {
+    path: PathBuf,
+    scale: f32,
+    smoothing: f32,
+    hysteresis: u8,
+    curve: Vec<BrightnessPoint>,
+    smoothed: Option<f32>,
+    applied: Option<u8>,
+    failing: bool,
+}
+
+impl AutoBrightness {
+    pub fn new(config: &AutoBrightnessConfig) -> Self {
+        Self {
+            path: PathBuf::from(&config.path),
+            scale: config.scale,
+            smoothing: config.smoothing,
+            hysteresis: config.hysteresis,
+            curve: config.curve.clone(),
+            smoothed: None,
+            applied: None,
+            failing: false,
+        }
+    }
+
+    fn read_lux(&self) -> Result<f32, String> {
+        let raw = std::fs::read_to_string(&self.path)
+            .map_err(|err| format!("unable to read {}: {err}", self.path.display()))?;
+        let value = raw
+            .trim()
+            .parse::<f32>()
+            .map_err(|err| format!("invalid sensor value in {}: {err}", self.path.display()))?;
+        Ok(value * self.scale)
+    }
+
+    /// Brightness picked by the last successful sample.
+    pub fn brightness(&self) -> Option<u8> {
+        self.applied
+    }
+
+    /// Read the sensor and return a new panel brightness when it moved far
+    /// enough from the last applied value.
+    pub fn sample(&mut self) -> Option<u8> {
+        let lux = match self.read_lux() {
+            Ok(lux) => {
+                self.failing = false;
+                lux
+            }
+            Err(err) => {
+                if !self.failing {
+                    log::warn!("ambient light sensor unavailable: {err}");
+                    self.failing = true;
+                }
+                return None;
+            }
+        };
+
+        // exponential moving average to ignore short flickers
+        let smoothed = match self.smoothed {
+            Some(prev) => prev + (lux - prev) * self.smoothing,
+            None => lux,
+        };
+        self.smoothed = Some(smoothed);
+
+        let target = map_curve(&self.curve, smoothed);
+        match self.applied {
+            Some(prev) if prev.abs_diff(target) <= self.hysteresis => None,
+            _ => {
+                self.applied = Some(target);
+                Some(target)
+            }
+        }
+    }
+}
+
+/// Piecewise-linear lookup of a brightness for `lux` on a curve sorted by lux.
+fn map_curve(curve: &[BrightnessPoint], lux: f32) -> u8 {
+    debug_assert!(
+        !curve.is_empty(),
+        "config::validate rejects empty auto brightness curves"
+    );
+    let (Some(first), Some(last)) = (curve.first(), curve.last()) else {
+        return 0;
+    };
+
+    if lux <= first.lux {
+        return first.brightness;
+    }
+    if lux >= last.lux {
+        return last.brightness;
+    }
+
+    for pair in curve.windows(2) {
+        let (lo, hi) = (&pair[0], &pair[1]);
+        if lux <= hi.lux {
+            let span = (hi.lux - lo.lux).max(f32::EPSILON);
+            let fract = (lux - lo.lux) / span;
+            let value =
+                lo.brightness as f32 + (hi.brightness as f32 - lo.brightness as f32) * fract;
+            return value.round().clamp(0.0, 255.0) as u8;
+        }
+    }
+
+    last.brightness
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn curve() -> Vec<BrightnessPoint> {
+        [(0.0, 10), (100.0, 50), (1000.0, 200)]
+            .map(|(lux, brightness)| BrightnessPoint { lux, brightness })
+            .to_vec()
+    }
+
+    /// A sensor file removed again when dropped.
+    struct FakeSensor(PathBuf);
+
+    impl FakeSensor {
+        fn set(&self, value: &str) {
+            std::fs::write(&self.0, format!("{value}\n")).unwrap();
+        }
+    }
+
+    impl Drop for FakeSensor {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    #[test]
+    fn curve_clamps_and_interpolates() {
+        let curve = curve();
+        assert_eq!(map_curve(&curve, -5.0), 10);
+        assert_eq!(map_curve(&curve, 0.0), 10);
+        assert_eq!(map_curve(&curve, 50.0), 30);
+        assert_eq!(map_curve(&curve, 550.0), 125);
+        assert_eq!(map_curve(&curve, 1000.0), 200);
+        assert_eq!(map_curve(&curve, 5000.0), 200);
+    }
+
+    #[test]
+    fn sample_smooths_with_hysteresis() {
+        let sensor =
+            FakeSensor(std::env::temp_dir().join(format!("ambient-test-{}", std::process::id())));
+        let mut auto = AutoBrightness::new(&AutoBrightnessConfig {
+            path: sensor.0.to_string_lossy().into_owned(),
+            scale: 1.0,
+            smoothing: 0.5,
+            hysteresis: 8,
+            curve: curve(),
+        });
+
+        sensor.set("100");
+        assert_eq!(auto.sample(), Some(50));
+        // averaged to 105 lux, within the hysteresis
+        sensor.set("110");
+        assert_eq!(auto.sample(), None);
+        // averaged to 552.5 lux
+        sensor.set("1000");
+        assert_eq!(auto.sample(), Some(125));
+        // a broken reading keeps the last brightness
+        sensor.set("garbage");
+        assert_eq!(auto.sample(), None);
+        assert_eq!(auto.brightness(), Some(125));
+    }
+}
@@ -1,18 +1,293 @@
-use std::path::Path;
+use std::{collections::HashMap, path::Path};
 
 use serde::{Deserialize, Serialize};
 
+use crate::{
+    gamma::GammaTable,
+    matrix::{self, MATRIX_HEIGHT, MATRIX_WIDTH},
+    metrics::Metric,
+    notify::Icon,
+    schedule::Schedule,
+};
+
 /// Top-level application configuration loaded from TOML.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
     pub general: GeneralConfig,
     pub widgets: Vec<WidgetConfig>,
+    /// Named alternative widget sets that schedules can switch to.
+    #[serde(default)]
+    pub layouts: HashMap<String, Vec<WidgetConfig>>,
+    #[serde(default)]
+    pub schedules: Vec<ScheduleConfig>,
+    /// Power-source profiles, the first matching entry is used.
+    #[serde(default)]
+    pub profiles: Vec<PowerProfileConfig>,
 }
 
 /// Global display options shared by all widgets.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GeneralConfig {
     pub brightness: i32,
+    #[serde(default)]
+    pub gamma: GammaConfig,
+    pub auto_brightness: Option<AutoBrightnessConfig>,
+    /// Delay between two frames in milliseconds.
+    #[serde(default = "default_update_interval")]
+    pub update_interval: u64,
+    /// Samples of every metric kept for history graphs.
+    #[serde(default = "default_history_length")]
+    pub history_length: usize,
+    /// Directory holding the hwmon chips read by temperature widgets and
+    /// `temp:` metrics.
+    #[serde(default = "default_hwmon")]
+    pub hwmon: String,
+    pub idle: Option<IdleConfig>,
+    #[serde(default)]
+    pub on_exit: ExitConfig,
+    /// Path of the control socket, `$XDG_RUNTIME_DIR` or `/tmp` by default.
+    pub control_socket: Option<String>,
+    pub desktop_notifications: Option<DesktopNotificationsConfig>,
+    pub dbus: Option<DbusConfig>,
+}
+
+/// Publish the `org.framework.LedMatrix` service.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DbusConfig {
+    #[serde(default)]
+    pub bus: BusKind,
+    /// D-Bus address to register on instead of `bus`.
+    pub bus_address: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BusKind {
+    #[default]
+    Session,
+    System,
+}
+
+/// Mirror `org.freedesktop.Notifications` messages from the session bus.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DesktopNotificationsConfig {
+    #[serde(default)]
+    pub panel: usize,
+    /// Only mirror these apps, every app when empty.
+    #[serde(default)]
+    pub allow_apps: Vec<String>,
+    #[serde(default)]
+    pub deny_apps: Vec<String>,
+    #[serde(default)]
+    pub min_urgency: Urgency,
+    /// Append the notification body to its summary.
+    #[serde(default)]
+    pub show_body: bool,
+    /// Longest text shown, in characters.
+    #[serde(default = "default_notification_length")]
+    pub max_length: usize,
+    /// Icon shown for notifications of an app, keyed by app name.
+    #[serde(default)]
+    pub icons: HashMap<String, Icon>,
+    /// Also mirror notifications with the `transient` hint, like volume
+    /// popups.
+    #[serde(default)]
+    pub transient: bool,
+    /// D-Bus address to monitor instead of the session bus.
+    pub bus_address: Option<String>,
+}
+
+fn default_notification_length() -> usize {
+    40
+}
+
+/// Urgency levels of the desktop notification spec.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Urgency {
+    Low,
+    #[default]
+    Normal,
+    Critical,
+}
+
+/// What the panels show after the service received SIGTERM/SIGINT.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ExitConfig {
+    #[serde(default)]
+    pub action: ExitAction,
+    /// Rows of the frame shown by the `pattern` action, `#` is lit, `+` is dim.
+    #[serde(default)]
+    pub pattern: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExitAction {
+    /// Leave the last frame on the panels.
+    Keep,
+    /// Turn every LED off.
+    #[default]
+    Clear,
+    /// Put the panels into their sleep state.
+    Sleep,
+    /// Draw `ExitConfig::pattern`.
+    Pattern,
+}
+
+fn default_history_length() -> usize {
+    120
+}
+
+fn default_update_interval() -> u64 {
+    500
+}
+
+/// Output curve applied to widget brightness levels before they are sent.
+///
+/// `lut` takes precedence over `exponent` when it is not empty. Its entries
+/// are evenly spaced, non-decreasing control points from level 0 to level 255.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GammaConfig {
+    #[serde(default = "default_gamma_exponent")]
+    pub exponent: f32,
+    #[serde(default)]
+    pub lut: Vec<u8>,
+}
+
+impl Default for GammaConfig {
+    fn default() -> Self {
+        Self {
+            exponent: default_gamma_exponent(),
+            lut: Vec::new(),
+        }
+    }
+}
+
+fn default_gamma_exponent() -> f32 {
+    1.0
+}
+
+/// Ambient light sensor settings for automatic panel brightness.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AutoBrightnessConfig {
+    #[serde(default = "default_als_path")]
+    pub path: String,
+    /// Multiplier converting the raw sensor value to lux.
+    #[serde(default = "default_als_scale")]
+    pub scale: f32,
+    /// Weight of a new reading in the moving average (0.0-1.0].
+    #[serde(default = "default_als_smoothing")]
+    pub smoothing: f32,
+    /// Minimum brightness change before the panels are updated.
+    #[serde(default = "default_als_hysteresis")]
+    pub hysteresis: u8,
+    pub curve: Vec<BrightnessPoint>,
+}
+
+/// One point of the lux to panel brightness curve.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BrightnessPoint {
+    pub lux: f32,
+    pub brightness: u8,
+}
+
+fn default_als_path() -> String {
+    "/sys/bus/iio/devices/iio:device0/in_illuminance_raw".to_string()
+}
+
+fn default_als_scale() -> f32 {
+    1.0
+}
+
+fn default_als_smoothing() -> f32 {
+    0.3
+}
+
+fn default_als_hysteresis() -> u8 {
+    8
+}
+
+/// Sleep the panels based on the logind session state.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IdleConfig {
+    #[serde(default = "default_true")]
+    pub when_locked: bool,
+    #[serde(default = "default_true")]
+    pub when_idle: bool,
+    #[serde(default = "default_true")]
+    pub when_lid_closed: bool,
+    /// Seat whose active session is watched.
+    #[serde(default = "default_seat")]
+    pub seat: String,
+    /// D-Bus address to query instead of the system bus.
+    pub bus_address: Option<String>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_seat() -> String {
+    "seat0".to_string()
+}
+
+/// A recurring time window that changes how the panels are driven.
+///
+/// Windows where `end` is before `start` wrap past midnight and belong to the
+/// weekday they start on. An empty `days` list matches every day.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduleConfig {
+    pub start: String,
+    pub end: String,
+    #[serde(default)]
+    pub days: Vec<String>,
+    #[serde(flatten)]
+    pub apply: DisplayOverride,
+}
+
+/// Display settings that temporarily replace the `general`/`widgets` ones.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DisplayOverride {
+    pub brightness: Option<u8>,
+    /// Name of an entry in `layouts` to show instead of `widgets`.
+    pub layout: Option<String>,
+    #[serde(default)]
+    pub sleep: bool,
+    /// Delay between two frames in milliseconds.
+    pub update_interval: Option<u64>,
+}
+
+impl DisplayOverride {
+    /// Layer `other` on top of `self`, preferring the fields `other` sets.
+    pub fn merge(&self, other: &DisplayOverride) -> DisplayOverride {
+        DisplayOverride {
+            brightness: other.brightness.or(self.brightness),
+            layout: other.layout.clone().or_else(|| self.layout.clone()),
+            sleep: self.sleep || other.sleep,
+            update_interval: other.update_interval.or(self.update_interval),
+        }
+    }
+}
+
+/// Display settings that apply while running from a given power source.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PowerProfileConfig {
+    pub name: String,
+    /// Power source that activates the profile, any source when unset.
+    pub power: Option<PowerSource>,
+    /// Only activate below this battery charge in percent.
+    pub below: Option<f32>,
+    #[serde(flatten)]
+    pub apply: DisplayOverride,
+}
+
+/// Where the laptop currently draws its power from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PowerSource {
+    Ac,
+    Battery,
 }
 
 /// Per-widget placement and setup configuration.
@@ -30,26 +305,408 @@ pub enum WidgetSetup {
     Cpu(WidgetCpuSetup),
     Memory(WidgetMemorySetup),
     Network(WidgetNetworkSetup),
-    Battery,
-    Clock,
+    Battery(WidgetBatterySetup),
+    Clock(WidgetClockSetup),
+    Stream(WidgetStreamSetup),
+    Graph(WidgetGraphSetup),
+    Link(WidgetLinkSetup),
+    Temperature(WidgetTemperatureSetup),
+    Fan(WidgetFanSetup),
+}
+
+impl WidgetSetup {
+    /// Name of the variant as written in the config.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            WidgetSetup::Cpu(_) => "Cpu",
+            WidgetSetup::Memory(_) => "Memory",
+            WidgetSetup::Network(_) => "Network",
+            WidgetSetup::Battery(_) => "Battery",
+            WidgetSetup::Clock(_) => "Clock",
+            WidgetSetup::Stream(_) => "Stream",
+            WidgetSetup::Graph(_) => "Graph",
+            WidgetSetup::Link(_) => "Link",
+            WidgetSetup::Temperature(_) => "Temperature",
+            WidgetSetup::Fan(_) => "Fan",
+        }
+    }
 }
 
 /// CPU widget-specific settings.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WidgetCpuSetup {
+    /// Draw a column per group of logical CPUs instead of a row per CPU.
+    #[serde(default)]
     pub merge_threads: bool,
+    #[serde(default)]
+    pub mode: CpuMode,
+    #[serde(default)]
+    pub group: CpuGroup,
+    /// Rows of the merged columns.
+    #[serde(default = "default_cpu_height")]
+    pub height: usize,
+}
+
+/// What the CPU widget draws.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CpuMode {
+    /// Usage as a row per CPU or a column per group.
+    #[default]
+    Bars,
+    /// One LED per group, brighter with more load, 9 groups per row.
+    Heatmap,
+    /// Overall usage as a single row.
+    Total,
+    /// Current frequency out of the maximum, laid out like `bars`.
+    Frequency,
+}
+
+/// Logical CPUs sharing a column when threads are merged, or a heatmap LED.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CpuGroup {
+    /// SMT siblings of one physical core.
+    #[default]
+    Core,
+    /// Cores sharing an L3 cache, one CCD or CCX on AMD processors.
+    Ccd,
+    /// Every logical CPU in one column.
+    All,
+}
+
+fn default_cpu_height() -> usize {
+    8
 }
 
 /// Memory widget-specific settings.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WidgetMemorySetup {
+    /// Add a row for swap usage.
+    #[serde(default = "default_true")]
     pub swap: bool,
+    #[serde(default)]
+    pub mode: MemoryMode,
+    /// Add a row for the zram compression ratio.
+    #[serde(default)]
+    pub zram: bool,
+    /// Add a row for the zswap compression ratio.
+    #[serde(default)]
+    pub zswap: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MemoryMode {
+    /// Used memory as one bar.
+    #[default]
+    Usage,
+    /// Used, buffers and cached memory as stacked segments of decreasing
+    /// brightness, free memory stays off.
+    Breakdown,
+}
+
+/// Battery widget-specific settings.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WidgetBatterySetup {
+    #[serde(default)]
+    pub mode: BatteryMode,
+    /// Power draw of a full bar in watts.
+    #[serde(default = "default_battery_max_power")]
+    pub max_power: f32,
+    /// Charge in percent at or below which a discharging battery blinks.
+    pub low: Option<f32>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BatteryMode {
+    /// Charge as a two row bar.
+    #[default]
+    Level,
+    /// Hours above minutes until empty, or dimmed until full.
+    Time,
+    /// Charge or discharge rate as a bar up to `max_power`.
+    Power,
+    /// Full capacity out of the design capacity as a bar.
+    Health,
+}
+
+fn default_battery_max_power() -> f32 {
+    60.0
+}
+
+/// Clock widget-specific settings.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WidgetClockSetup {
+    #[serde(default)]
+    pub style: ClockStyle,
+    /// What the digital style shows.
+    #[serde(default)]
+    pub view: ClockView,
+    /// Hours from 1 to 12 with a dot for AM or PM.
+    #[serde(default)]
+    pub twelve_hour: bool,
+    #[serde(default)]
+    pub seconds: ClockSeconds,
+    /// Blink a colon between hours and minutes.
+    #[serde(default)]
+    pub blink_colon: bool,
+    /// IANA time zone like `UTC` or `America/New_York`, local time when unset.
+    pub timezone: Option<String>,
+    /// First and last hour of the working day marked by the day style.
+    pub work_hours: Option<[u32; 2]>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ClockStyle {
+    /// Numbers in the 9x11 block, see `view`.
+    #[default]
+    Digital,
+    /// The full panel height filling up over the day on the left and over
+    /// the hour on the right.
+    Fill,
+    /// A 9x9 dial with an hour and a minute hand.
+    Analog,
+    /// A row per hour of the day filling up as it passes.
+    Day,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ClockView {
+    /// Hours above minutes.
+    #[default]
+    Time,
+    /// Day above month above the weekday.
+    Date,
+}
+
+/// How the time view shows the seconds.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ClockSeconds {
+    #[default]
+    Off,
+    /// A row filling up over the minute.
+    Bar,
+    /// A dot moving along a row over the minute.
+    Dots,
 }
 
 /// Network widget-specific settings.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WidgetNetworkSetup {
+    /// Glob patterns of the interfaces to show, all interfaces when empty.
+    #[serde(default)]
     pub devices: Vec<String>,
+    /// Glob patterns of interfaces never shown, wins over `devices`. Falls
+    /// back to [`DEFAULT_NETWORK_EXCLUDE`] when unset and `devices` is empty.
+    pub exclude: Option<Vec<String>>,
+    /// Only show the interface of the default route.
+    #[serde(default)]
+    pub default_route: bool,
+    /// Draw a download and upload row per interface instead of their sum.
+    #[serde(default)]
+    pub per_interface: bool,
+    /// Interfaces drawn with `per_interface`, further ones are left out.
+    #[serde(default = "default_network_max_interfaces")]
+    pub max_interfaces: usize,
+    /// Full scale download in Mbit/s.
+    #[serde(default = "default_network_max_download")]
+    pub max_download: f32,
+    /// Full scale upload in Mbit/s.
+    #[serde(default = "default_network_max_upload")]
+    pub max_upload: f32,
+    /// Scale each direction to a decaying peak instead of the fixed maximum.
+    #[serde(default)]
+    pub auto_scale: bool,
+    /// Scale the bars logarithmically so small rates stay visible.
+    #[serde(default)]
+    pub log_scale: bool,
+}
+
+/// Interfaces left out when neither `devices` nor `exclude` are configured.
+pub const DEFAULT_NETWORK_EXCLUDE: [&str; 4] = ["lo", "virbr*", "docker*", "veth*"];
+
+fn default_network_max_download() -> f32 {
+    500.0
+}
+
+fn default_network_max_upload() -> f32 {
+    100.0
+}
+
+fn default_network_max_interfaces() -> usize {
+    2
+}
+
+/// Link widget-specific settings, interfaces are picked like for the network
+/// widget.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WidgetLinkSetup {
+    #[serde(default)]
+    pub devices: Vec<String>,
+    pub exclude: Option<Vec<String>>,
+    /// Rows drawn, further interfaces are left out.
+    #[serde(default = "default_network_max_interfaces")]
+    pub max_interfaces: usize,
+}
+
+/// Temperature widget-specific settings.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WidgetTemperatureSetup {
+    /// Glob patterns matched against the sensor label, or `chip:label`. Each
+    /// pattern gets a row, or a number, showing its hottest match.
+    pub sensors: Vec<String>,
+    #[serde(default)]
+    pub style: TemperatureStyle,
+    /// Temperature of an empty bar in °C.
+    #[serde(default = "default_temperature_min")]
+    pub min: f32,
+    /// Temperature of a full bar in °C.
+    #[serde(default = "default_temperature_max")]
+    pub max: f32,
+    /// Sensors at or above this temperature in °C blink.
+    pub critical: Option<f32>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TemperatureStyle {
+    /// A row per sensor filling from `min` to `max`.
+    #[default]
+    Bars,
+    /// Two digit numbers stacked below each other.
+    Numerals,
+}
+
+fn default_temperature_min() -> f32 {
+    30.0
+}
+
+fn default_temperature_max() -> f32 {
+    100.0
+}
+
+/// Fan widget-specific settings.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WidgetFanSetup {
+    /// Glob patterns matched against the fan label, or `chip:label`, all fans
+    /// when empty. Fans without a label are named `fan<N>`.
+    #[serde(default)]
+    pub fans: Vec<String>,
+    #[serde(default)]
+    pub style: FanStyle,
+    /// Speed of a full bar, or the fastest spin, in RPM.
+    #[serde(default = "default_fan_max_rpm")]
+    pub max_rpm: f32,
+    /// Fans drawn, further ones are left out.
+    #[serde(default = "default_fan_max_fans")]
+    pub max_fans: usize,
+    /// Directory holding the hwmon chips.
+    #[serde(default = "default_hwmon")]
+    pub hwmon: String,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FanStyle {
+    /// A row per fan filling up to `max_rpm`.
+    #[default]
+    Bar,
+    /// A 5x5 propeller per fan turning faster with the RPM.
+    Spin,
+}
+
+fn default_fan_max_rpm() -> f32 {
+    6000.0
+}
+
+fn default_fan_max_fans() -> usize {
+    2
+}
+
+fn default_hwmon() -> String {
+    "/sys/class/hwmon".to_string()
+}
+
+/// Stream widget-specific settings.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WidgetStreamSetup {
+    /// `-` for stdin, otherwise the path of a file or named pipe.
+    #[serde(default = "default_stream_source")]
+    pub source: String,
+    #[serde(default)]
+    pub format: StreamFormat,
+    #[serde(default = "default_stream_width")]
+    pub width: usize,
+    #[serde(default = "default_stream_height")]
+    pub height: usize,
+}
+
+fn default_stream_source() -> String {
+    "-".to_string()
+}
+
+fn default_stream_width() -> usize {
+    MATRIX_WIDTH
+}
+
+fn default_stream_height() -> usize {
+    MATRIX_HEIGHT
+}
+
+/// Encoding of the frames written to a stream widget.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StreamFormat {
+    /// One brightness byte per LED, row-major.
+    #[default]
+    Raw,
+    /// One bit per LED, packed like `matrix::encode`.
+    Packed,
+    /// Binary PGM (P5) images of the widget's size.
+    Pgm,
+}
+
+/// Graph widget-specific settings.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WidgetGraphSetup {
+    pub metric: Metric,
+    #[serde(default)]
+    pub style: GraphStyle,
+    #[serde(default = "default_graph_width")]
+    pub width: usize,
+    #[serde(default = "default_graph_height")]
+    pub height: usize,
+    /// Value at the bottom of the graph.
+    #[serde(default)]
+    pub min: f32,
+    /// Value at the top, percentages default to 100 and rates scale to the
+    /// largest value shown.
+    pub max: Option<f32>,
+    /// Value marked with a dim line across the graph.
+    pub threshold: Option<f32>,
+}
+
+fn default_graph_width() -> usize {
+    MATRIX_WIDTH
+}
+
+fn default_graph_height() -> usize {
+    8
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GraphStyle {
+    /// One column per sample filled from the bottom.
+    #[default]
+    Bars,
+    /// Only the top of every column.
+    Line,
 }
 
 /// Load and deserialize a configuration file from a path.
@@ -59,5 +716,157 @@ pub fn load(path: impl AsRef<Path>) -> Result<Config, String> {
         std::fs::read_to_string(path).map_err(|err| format!("Unable to load config: {}", err))?;
     let config = toml::from_str::<Config>(&config_str)
         .map_err(|err| format!("Unable to load config: {}", err))?;
+    validate(&config).map_err(|err| format!("Invalid config: {}", err))?;
     Ok(config)
 }
+
+/// Check semantic constraints that the TOML schema alone cannot express.
+pub(crate) fn validate(config: &Config) -> Result<(), String> {
+    if config.general.update_interval == 0 {
+        return Err("update_interval must be greater than 0".to_string());
+    }
+
+    GammaTable::from_config(&config.general.gamma)?;
+
+    if let Some(auto) = &config.general.auto_brightness {
+        if auto.curve.is_empty() {
+            return Err("auto_brightness curve needs at least 1 point".to_string());
+        }
+        if auto.curve.windows(2).any(|pair| pair[0].lux > pair[1].lux) {
+            return Err("auto_brightness curve points must be sorted by lux".to_string());
+        }
+        if !(auto.smoothing > 0.0 && auto.smoothing <= 1.0) {
+            return Err(format!(
+                "auto_brightness smoothing must be in (0, 1], got {}",
+                auto.smoothing
+            ));
+        }
+    }
+
+    if let Some(desktop) = &config.general.desktop_notifications {
+        if desktop.max_length == 0 {
+            return Err("desktop_notifications max_length must be greater than 0".to_string());
+        }
+    }
+
+    let on_exit = &config.general.on_exit;
+    if on_exit.action == ExitAction::Pattern {
+        matrix::from_text_art(&on_exit.pattern)
+            .map_err(|err| format!("invalid on_exit pattern: {err}"))?;
+    }
+
+    for widget in config
+        .widgets
+        .iter()
+        .chain(config.layouts.values().flatten())
+    {
+        match &widget.setup {
+            WidgetSetup::Stream(stream) if stream.width == 0 || stream.height == 0 => {
+                return Err("stream widget width and height must be greater than 0".to_string());
+            }
+            WidgetSetup::Network(network) => {
+                if network.per_interface && network.max_interfaces == 0 {
+                    return Err("network widget max_interfaces must be greater than 0".to_string());
+                }
+                if [network.max_download, network.max_upload]
+                    .iter()
+                    .any(|max| !max.is_finite() || *max <= 0.0)
+                {
+                    return Err("network widget max rates must be greater than 0".to_string());
+                }
+            }
+            WidgetSetup::Cpu(cpu) if cpu.merge_threads && cpu.height == 0 => {
+                return Err("cpu widget height must be greater than 0".to_string());
+            }
+            WidgetSetup::Temperature(temperature) => {
+                if temperature.sensors.is_empty() {
+                    return Err("temperature widget needs at least 1 sensor".to_string());
+                }
+                if temperature.max <= temperature.min {
+                    return Err(format!(
+                        "temperature widget max must be greater than min ({})",
+                        temperature.min
+                    ));
+                }
+            }
+            WidgetSetup::Fan(fan) => {
+                if fan.max_fans == 0 {
+                    return Err("fan widget max_fans must be greater than 0".to_string());
+                }
+                if !fan.max_rpm.is_finite() || fan.max_rpm <= 0.0 {
+                    return Err("fan widget max_rpm must be greater than 0".to_string());
+                }
+            }
+            WidgetSetup::Battery(battery)
+                if !battery.max_power.is_finite() || battery.max_power <= 0.0 =>
+            {
+                return Err("battery widget max_power must be greater than 0".to_string());
+            }
+            WidgetSetup::Clock(clock) => {
+                if let Some(timezone) = &clock.timezone {
+                    timezone
+                        .parse::<chrono_tz::Tz>()
+                        .map_err(|err| format!("clock widget timezone: {err}"))?;
+                }
+                if let Some([start, end]) = clock.work_hours {
+                    if start >= end || end > 24 {
+                        return Err(format!(
+                            "clock widget work_hours must be increasing hours up to 24, got [{start}, {end}]"
+                        ));
+                    }
+                }
+            }
+            WidgetSetup::Link(link) if link.max_interfaces == 0 => {
+                return Err("link widget max_interfaces must be greater than 0".to_string());
+            }
+            WidgetSetup::Graph(graph) => {
+                if graph.width == 0 || graph.height == 0 {
+                    return Err("graph widget width and height must be greater than 0".to_string());
+                }
+                if graph.max.is_some_and(|max| max <= graph.min) {
+                    return Err(format!(
+                        "graph widget max must be greater than min ({})",
+                        graph.min
+                    ));
+                }
+                if graph.max.is_none() && graph.metric.is_percent() && graph.min >= 100.0 {
+                    return Err(format!(
+                        "graph widget min of {} must be below 100 or come with a max",
+                        graph.metric
+                    ));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    for schedule in config.schedules.iter() {
+        Schedule::from_config(schedule)?;
+        validate_override(config, &schedule.apply)?;
+    }
+
+    for (idx, profile) in config.profiles.iter().enumerate() {
+        if config.profiles[..idx]
+            .iter()
+            .any(|p| p.name == profile.name)
+        {
+            return Err(format!("duplicate profile name \"{}\"", profile.name));
+        }
+        validate_override(config, &profile.apply)?;
+    }
+
+    Ok(())
+}
+
+fn validate_override(config: &Config, apply: &DisplayOverride) -> Result<(), String> {
+    if apply.update_interval == Some(0) {
+        return Err("update_interval must be greater than 0".to_string());
+    }
+
+    match &apply.layout {
+        Some(layout) if !config.layouts.contains_key(layout) => {
+            Err(format!("unknown layout \"{layout}\""))
+        }
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,176 @@
+use clap::{Args, Subcommand};
+
+use crate::{
+    ipc::{self, Request, Response},
+    notify::{Icon, Priority},
+};
+
+/// Control a running daemon over its socket.
+#[derive(Args)]
+pub struct CtlArgs {
+    /// Control socket of the daemon
+    #[arg(long)]
+    socket: Option<String>,
+
+    #[command(subcommand)]
+    action: CtlAction,
+}
+
+#[derive(Subcommand)]
+enum CtlAction {
+    /// Show panels, widgets and the current display state
+    Status,
+    /// Set the panel brightness (0-255), or "auto" to drop the override
+    Brightness { value: String },
+    /// Put the panels to sleep until woken
+    Sleep,
+    /// Wake the panels, even during a sleep schedule
+    Wake,
+    /// Force a power profile by name, or "auto" to drop the override
+    Profile { name: String },
+    /// Drop every manual override and temporary frame
+    Reset,
+    /// Reload the daemon's config file
+    Reload,
+    /// Show a text art frame on a panel for a while
+    Show {
+        #[arg(long, default_value_t = 0)]
+        panel: usize,
+        /// How long to show the frame in milliseconds
+        #[arg(long, default_value_t = 5000)]
+        duration: u64,
+        /// Frame rows from the top, `#` is lit, `+` is dim and `.` is off
+        rows: Vec<String>,
+    },
+}
+
+/// Flash a message on a panel of the running daemon.
+#[derive(Args)]
+pub struct NotifyArgs {
+    /// Control socket of the daemon
+    #[arg(long)]
+    socket: Option<String>,
+
+    #[arg(long, default_value_t = 0)]
+    panel: usize,
+
+    #[arg(long, value_enum)]
+    icon: Option<Icon>,
+
+    /// Higher priorities interrupt the message currently shown
+    #[arg(long, value_enum, default_value_t = Priority::Normal)]
+    priority: Priority,
+
+    /// How long to show the message in milliseconds, defaults to one scroll
+    #[arg(long)]
+    duration: Option<u64>,
+
+    text: String,
+}
+
+fn parse_auto<T>(
+    value: &str,
+    parse: impl FnOnce(&str) -> Result<T, String>,
+) -> Result<Option<T>, String> {
+    if value == "auto" {
+        Ok(None)
+    } else {
+        parse(value).map(Some)
+    }
+}
+
+fn build_request(action: CtlAction) -> Result<Request, String> {
+    Ok(match action {
+        CtlAction::Status => Request::Status,
+        CtlAction::Brightness { value } => Request::Brightness {
+            value: parse_auto(&value, |v| {
+                v.parse::<u8>()
+                    .map_err(|_| format!("brightness must be 0-255 or auto, got \"{v}\""))
+            })?,
+        },
+        CtlAction::Sleep => Request::Sleep,
+        CtlAction::Wake => Request::Wake,
+        CtlAction::Profile { name } => Request::Profile {
+            name: parse_auto(&name, |v| Ok(v.to_string()))?,
+        },
+        CtlAction::Reset => Request::Reset,
+        CtlAction::Reload => Request::Reload,
+        CtlAction::Show {
+            panel,
+            duration,
+            rows,
+        } => Request::Show {
+            panel,
+            rows,
+            pixels: Vec::new(),
+            duration_ms: duration,
+        },
+    })
+}
+
+fn print_response(response: &Response) {
+    let Some(status) = &response.status else {
+        println!("ok");
+        return;
+    };
+
+    println!(
+        "brightness: {}",
+        status
+            .brightness
+            .map(|b| b.to_string())
+            .unwrap_or_else(|| "-".to_string())
+    );
+    println!("asleep: {}", status.asleep);
+    println!("profile: {}", status.profile.as_deref().unwrap_or("-"));
+    println!("layout: {}", status.layout.as_deref().unwrap_or("default"));
+    println!("fps: {:.1}", status.fps);
+    println!("panels:");
+    for panel in status.panels.iter() {
+        println!("  {}: {} ({})", panel.index, panel.port, panel.firmware);
+    }
+    println!("widgets:");
+    for widget in status.widgets.iter() {
+        println!(
+            "  {} on panel {} at ({}, {})",
+            widget.kind, widget.panel, widget.x, widget.y
+        );
+    }
+}
+
+fn send(socket: Option<String>, request: &Request) -> Result<Response, String> {
+    let socket = socket
+        .map(Into::into)
+        .unwrap_or_else(ipc::default_socket_path);
+
+    let response = ipc::request(&socket, request)?;
+    if !response.ok {
+        return Err(response
+            .error
+            .unwrap_or_else(|| "request failed".to_string()));
+    }
+    Ok(response)
+}
+
+/// Send the requested command and print the daemon's answer.
+pub fn run(args: CtlArgs) -> Result<(), String> {
+    let request = build_request(args.action)?;
+    let response = send(args.socket, &request)?;
+    print_response(&response);
+    Ok(())
+}
+
+/// Hand a notification to the daemon.
+pub fn notify(args: NotifyArgs) -> Result<(), String> {
+    send(
+        args.socket,
+        &Request::Notify {
+            panel: args.panel,
+            text: args.text,
+            icon: args.icon,
+            priority: args.priority,
+            duration_ms: args.duration,
+        },
+    )?;
+    Ok(())
+}
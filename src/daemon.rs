@@ -0,0 +1,612 @@
+use std::time::{Duration, Instant};
+
+use chrono::Local;
+
+use crate::{
+    ambient::AutoBrightness,
+    config::{self, Config, DisplayOverride, ExitAction, WidgetConfig},
+    gamma::GammaTable,
+    idle::IdleMonitor,
+    ipc::{PanelStatus, Request, Response, Status, WidgetStatus},
+    ledmatrix::LedMatrix,
+    matrix::{self, MATRIX_HEIGHT, MATRIX_WIDTH},
+    metrics::{Metrics, MetricsHandle},
+    notify::{self, Notification, Notifier},
+    power::PowerMonitor,
+    schedule::{self, Schedule},
+    widget::{
+        BatteryWidget, ClockWidget, CpuWidget, FanWidget, GraphWidget, LinkWidget, MemoryWidget,
+        NetworkWidget, StreamWidget, TemperatureWidget, Widget,
+    },
+};
+
+/// Widgets paired with the placement they were created from.
+pub type WidgetList = Vec<(WidgetConfig, Box<dyn Widget>)>;
+
+/// Instantiate the widgets described by a widget set, reading their data
+/// from `metrics`.
+pub fn load_widgets(configs: &[WidgetConfig], metrics: &MetricsHandle) -> WidgetList {
+    let mut widgets: WidgetList = Vec::new();
+    for widget in configs.iter() {
+        match &widget.setup {
+            config::WidgetSetup::Cpu(cfg) => {
+                widgets.push((
+                    widget.clone(),
+                    Box::new(CpuWidget::new(cfg, Box::new(metrics.clone()))),
+                ));
+            }
+            config::WidgetSetup::Memory(cfg) => {
+                widgets.push((
+                    widget.clone(),
+                    Box::new(MemoryWidget::new(cfg, Box::new(metrics.clone()))),
+                ));
+            }
+            config::WidgetSetup::Network(cfg) => {
+                widgets.push((
+                    widget.clone(),
+                    Box::new(NetworkWidget::new(cfg, Box::new(metrics.clone()))),
+                ));
+            }
+            config::WidgetSetup::Battery(cfg) => {
+                widgets.push((
+                    widget.clone(),
+                    Box::new(BatteryWidget::new(cfg, Box::new(metrics.clone()))),
+                ));
+            }
+            config::WidgetSetup::Clock(cfg) => {
+                widgets.push((widget.clone(), Box::new(ClockWidget::new(cfg))));
+            }
+            config::WidgetSetup::Stream(cfg) => {
+                widgets.push((widget.clone(), Box::new(StreamWidget::new(cfg))));
+            }
+            config::WidgetSetup::Graph(cfg) => {
+                widgets.push((
+                    widget.clone(),
+                    Box::new(GraphWidget::new(cfg, metrics.clone())),
+                ));
+            }
+            config::WidgetSetup::Link(cfg) => {
+                widgets.push((widget.clone(), Box::new(LinkWidget::new(cfg))));
+            }
+            config::WidgetSetup::Temperature(cfg) => {
+                widgets.push((
+                    widget.clone(),
+                    Box::new(TemperatureWidget::new(cfg, Box::new(metrics.clone()))),
+                ));
+            }
+            config::WidgetSetup::Fan(cfg) => {
+                widgets.push((widget.clone(), Box::new(FanWidget::new(cfg))));
+            }
+        }
+    }
+    widgets
+}
+
+pub fn validate_widget_placements(widgets: &WidgetList, panel_count: usize) -> Result<(), String> {
+    for (cfg, widget) in widgets {
+        if cfg.panel >= panel_count {
+            return Err(format!(
+                "widget targets panel {} but only {} panel(s) were detected",
+                cfg.panel, panel_count
+            ));
+        }
+
+        let shape = widget.get_shape();
+        let x_end = cfg
+            .x
+            .checked_add(shape.x)
+            .ok_or_else(|| "widget x position overflowed usize".to_string())?;
+        let y_end = cfg
+            .y
+            .checked_add(shape.y)
+            .ok_or_else(|| "widget y position overflowed usize".to_string())?;
+
+        if x_end > MATRIX_WIDTH || y_end > MATRIX_HEIGHT {
+            return Err(format!(
+                "widget at panel {} with origin ({}, {}) and shape {}x{} exceeds panel bounds {}x{}",
+                cfg.panel, cfg.x, cfg.y, shape.x, shape.y, MATRIX_WIDTH, MATRIX_HEIGHT
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Update every widget placed on `panel` and compose them into one frame.
+pub fn compose(widgets: &mut WidgetList, panel: usize) -> matrix::Matrix {
+    let mut dots = [[0; MATRIX_WIDTH]; MATRIX_HEIGHT];
+    for (config, widget) in widgets.iter_mut().filter(|(c, _)| c.panel == panel) {
+        widget.update();
+        dots = matrix::emplace(dots, widget.as_ref(), config.x, config.y);
+    }
+    dots
+}
+
+/// Frame pushed over the control socket that hides a panel's widgets.
+struct Overlay {
+    panel: usize,
+    frame: matrix::Matrix,
+    until: Instant,
+}
+
+/// Display settings forced over the control socket.
+#[derive(Default)]
+struct ManualOverride {
+    brightness: Option<u8>,
+    sleep: Option<bool>,
+    profile: Option<String>,
+}
+
+/// Everything derived from a config that can fail to load.
+struct Setup {
+    gamma: GammaTable,
+    schedules: Vec<Schedule>,
+    widgets: WidgetList,
+}
+
+/// State of the running widget loop for a set of detected panels.
+pub struct Daemon {
+    config: Config,
+    /// File `Reload` reads the config from again.
+    config_path: String,
+    mats: Vec<LedMatrix>,
+    widgets: WidgetList,
+    layout: Option<String>,
+    schedules: Vec<Schedule>,
+    auto_brightness: Option<AutoBrightness>,
+    power: PowerMonitor,
+    idle: Option<IdleMonitor>,
+    profile: Option<String>,
+    manual: ManualOverride,
+    overlays: Vec<Overlay>,
+    notifier: Notifier,
+    metrics: MetricsHandle,
+    /// Last composed widget frame per panel, reused between widget updates.
+    widget_frames: Vec<matrix::Matrix>,
+    last_widget_update: Option<Instant>,
+    /// Last frame sent per panel, identical frames aren't sent again.
+    drawn: Vec<Option<matrix::Matrix>>,
+    brightness: Option<u8>,
+    update_interval: Duration,
+    asleep: bool,
+    last_frame: Option<Instant>,
+    fps: f32,
+}
+
+impl Daemon {
+    pub fn new(config: Config, config_path: &str, mats: Vec<LedMatrix>) -> Result<Self, String> {
+        let metrics = MetricsHandle::new(Metrics::new(
+            config.general.history_length,
+            &config.general.hwmon,
+        ));
+        let setup = Self::setup(&config, mats.len(), &metrics)?;
+        Ok(Self::with_setup(
+            config,
+            config_path.to_string(),
+            mats,
+            setup,
+            metrics,
+        ))
+    }
+
+    fn setup(
+        config: &Config,
+        panel_count: usize,
+        metrics: &MetricsHandle,
+    ) -> Result<Setup, String> {
+        // check every widget set up front so a schedule can't switch to a
+        // layout that doesn't fit the detected panels, on scratch metrics so
+        // the sources of inactive layouts aren't sampled every tick
+        if !config.layouts.is_empty() {
+            let scratch = MetricsHandle::new(Metrics::new(1, &config.general.hwmon));
+            for layout in config.layouts.values() {
+                validate_widget_placements(&load_widgets(layout, &scratch), panel_count)?;
+            }
+        }
+        // sources only the replaced widgets read stop being sampled, the
+        // current ones watch theirs again on their next read
+        metrics.unwatch();
+        let widgets = load_widgets(&config.widgets, metrics);
+        validate_widget_placements(&widgets, panel_count)?;
+
+        Ok(Setup {
+            gamma: GammaTable::from_config(&config.general.gamma)?,
+            schedules: schedule::load(&config.schedules)?,
+            widgets,
+        })
+    }
+
+    fn with_setup(
+        config: Config,
+        config_path: String,
+        mut mats: Vec<LedMatrix>,
+        setup: Setup,
+        metrics: MetricsHandle,
+    ) -> Self {
+        for mat in mats.iter_mut() {
+            mat.set_gamma(setup.gamma.clone());
+        }
+
+        Self {
+            schedules: setup.schedules,
+            auto_brightness: config
+                .general
+                .auto_brightness
+                .as_ref()
+                .map(AutoBrightness::new),
+            widgets: setup.widgets,
+            layout: None,
+            power: PowerMonitor::new(),
+            idle: config.general.idle.as_ref().map(IdleMonitor::new),
+            profile: None,
+            manual: ManualOverride::default(),
+            overlays: Vec::new(),
+            notifier: Notifier::default(),
+            metrics,
+            widget_frames: vec![[[0; MATRIX_WIDTH]; MATRIX_HEIGHT]; mats.len()],
+            last_widget_update: None,
+            drawn: vec![None; mats.len()],
+            brightness: None,
+            update_interval: Duration::from_millis(config.general.update_interval),
+            asleep: false,
+            last_frame: None,
+            fps: 0.0,
+            mats,
+            config,
+            config_path,
+        }
+    }
+
+    /// The config in use, including the changes of the last reload.
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Switch to a new config while keeping the connected panels.
+    pub fn reload(&mut self, config: Config) -> Result<(), String> {
+        // history survives a reload
+        let metrics = self.metrics.clone();
+        metrics.set_history_length(config.general.history_length);
+        let setup = Self::setup(&config, self.mats.len(), &metrics)?;
+        let mats = std::mem::take(&mut self.mats);
+        let mut manual = std::mem::take(&mut self.manual);
+        let asleep = self.asleep;
+        if manual
+            .profile
+            .as_ref()
+            .is_some_and(|name| !config.profiles.iter().any(|p| &p.name == name))
+        {
+            manual.profile = None;
+        }
+
+        let config_path = std::mem::take(&mut self.config_path);
+        *self = Self::with_setup(config, config_path, mats, setup, metrics);
+        self.manual = manual;
+        self.asleep = asleep;
+        log::info!("configuration reloaded");
+        Ok(())
+    }
+
+    /// Delay the caller should wait before the next `tick`.
+    ///
+    /// Scrolling notifications need more frames than widgets, widgets are
+    /// still only updated every configured interval.
+    pub fn update_interval(&self) -> Duration {
+        if self.notifier.is_animating() {
+            self.update_interval.min(notify::SCROLL_STEP)
+        } else {
+            self.update_interval
+        }
+    }
+
+    /// Combine the active power profile with the schedule that covers the
+    /// current local time, the schedule wins where both set a field.
+    fn active_override(&mut self) -> DisplayOverride {
+        let profile = match &self.manual.profile {
+            Some(name) => self.config.profiles.iter().find(|p| &p.name == name),
+            None => self.power.active(&self.config.profiles),
+        };
+        let profile_name = profile.map(|profile| profile.name.clone());
+        if profile_name != self.profile {
+            log::info!(
+                "power profile changed to {}",
+                profile_name.as_deref().unwrap_or("none")
+            );
+            self.profile = profile_name;
+        }
+
+        let base = profile
+            .map(|profile| profile.apply.clone())
+            .unwrap_or_default();
+        let mut apply = match schedule::active(&self.schedules, Local::now().naive_local()) {
+            Some(schedule) => base.merge(&schedule.apply),
+            None => base,
+        };
+
+        if self.idle.as_mut().is_some_and(IdleMonitor::should_sleep) {
+            apply.sleep = true;
+        }
+        if let Some(brightness) = self.manual.brightness {
+            apply.brightness = Some(brightness);
+        }
+        if let Some(sleep) = self.manual.sleep {
+            apply.sleep = sleep;
+        }
+        apply
+    }
+
+    fn apply_layout(&mut self, layout: Option<String>) {
+        if layout == self.layout {
+            return;
+        }
+
+        log::info!(
+            "switching to layout {}",
+            layout.as_deref().unwrap_or("default")
+        );
+        let configs = match &layout {
+            Some(name) => self.config.layouts.get(name).cloned().unwrap_or_default(),
+            None => self.config.widgets.clone(),
+        };
+        self.metrics.unwatch();
+        self.widgets = load_widgets(&configs, &self.metrics);
+        self.layout = layout;
+        self.last_widget_update = None;
+    }
+
+    fn apply_brightness(&mut self, apply: &DisplayOverride) -> Result<(), String> {
+        if let Some(auto) = self.auto_brightness.as_mut() {
+            auto.sample();
+        }
+
+        let base = self
+            .auto_brightness
+            .as_ref()
+            .and_then(AutoBrightness::brightness)
+            .unwrap_or(self.config.general.brightness.clamp(0, 255) as u8);
+        let target = apply.brightness.unwrap_or(base);
+
+        if self.brightness != Some(target) {
+            log::debug!("setting panel brightness to {target}");
+            for mat in self.mats.iter_mut() {
+                mat.set_full_brightness(target)?;
+            }
+            self.brightness = Some(target);
+        }
+
+        Ok(())
+    }
+
+    fn apply_sleep(&mut self, sleep: bool) -> Result<(), String> {
+        if sleep == self.asleep {
+            return Ok(());
+        }
+
+        for mat in self.mats.iter_mut() {
+            if sleep {
+                mat.sleep()?;
+            } else {
+                mat.wake()?;
+            }
+        }
+        log::info!("panels {}", if sleep { "asleep" } else { "awake" });
+        self.asleep = sleep;
+        if !sleep {
+            // the firmware may have reset while asleep, resend everything
+            self.brightness = None;
+            self.drawn.iter_mut().for_each(|frame| *frame = None);
+        }
+        Ok(())
+    }
+
+    /// Evaluate schedules and brightness, then redraw every panel once.
+    pub fn tick(&mut self) -> Result<(), String> {
+        let apply = self.active_override();
+        self.update_interval = Duration::from_millis(
+            apply
+                .update_interval
+                .unwrap_or(self.config.general.update_interval),
+        );
+
+        self.apply_sleep(apply.sleep)?;
+        if self.asleep {
+            return Ok(());
+        }
+
+        self.apply_layout(apply.layout.clone());
+        self.apply_brightness(&apply)?;
+
+        let now = Instant::now();
+        let widgets_due = self.last_widget_update.is_none_or(|last| {
+            now.duration_since(last) + Duration::from_millis(1) >= self.update_interval
+        });
+        if widgets_due {
+            self.last_widget_update = Some(now);
+            self.metrics.sample();
+        }
+
+        self.overlays.retain(|overlay| overlay.until > now);
+        for (idx, mat) in self.mats.iter_mut().enumerate() {
+            // widgets keep updating underneath notifications so they don't
+            // show stale values once the panel is theirs again
+            if widgets_due {
+                self.widget_frames[idx] = compose(&mut self.widgets, idx);
+            }
+
+            let notification = self.notifier.frame(idx, now);
+            let dots = match self.overlays.iter().find(|overlay| overlay.panel == idx) {
+                Some(overlay) => overlay.frame,
+                None => notification.unwrap_or(self.widget_frames[idx]),
+            };
+
+            if self.drawn[idx] != Some(dots) {
+                mat.draw_matrix(dots)?;
+                self.drawn[idx] = Some(dots);
+            }
+        }
+
+        if let Some(last) = self.last_frame {
+            let fps = 1.0 / now.duration_since(last).as_secs_f32().max(0.001);
+            self.fps = if self.fps > 0.0 {
+                self.fps * 0.8 + fps * 0.2
+            } else {
+                fps
+            };
+        }
+        self.last_frame = Some(now);
+
+        Ok(())
+    }
+
+    fn status(&self) -> Status {
+        Status {
+            panels: self
+                .mats
+                .iter()
+                .enumerate()
+                .map(|(index, mat)| PanelStatus {
+                    index,
+                    port: mat.port_info.port_name.clone(),
+                    firmware: mat.fw_version.clone(),
+                })
+                .collect(),
+            widgets: self
+                .widgets
+                .iter()
+                .map(|(cfg, _)| WidgetStatus {
+                    panel: cfg.panel,
+                    x: cfg.x,
+                    y: cfg.y,
+                    kind: cfg.setup.kind().to_string(),
+                })
+                .collect(),
+            fps: self.fps,
+            brightness: self.brightness,
+            asleep: self.asleep,
+            profile: self.profile.clone(),
+            layout: self.layout.clone(),
+        }
+    }
+
+    /// Answer a control socket request.
+    pub fn handle(&mut self, request: Request) -> Response {
+        match request {
+            Request::Status => Response::status(self.status()),
+            Request::Brightness { value } => {
+                self.manual.brightness = value;
+                Response::ok()
+            }
+            Request::Sleep => {
+                self.manual.sleep = Some(true);
+                Response::ok()
+            }
+            Request::Wake => {
+                self.manual.sleep = Some(false);
+                Response::ok()
+            }
+            Request::Profile { name } => {
+                if let Some(name) = &name {
+                    if !self.config.profiles.iter().any(|p| &p.name == name) {
+                        return Response::error(format!("unknown profile \"{name}\""));
+                    }
+                }
+                self.manual.profile = name;
+                Response::ok()
+            }
+            Request::Reset => {
+                self.manual = ManualOverride::default();
+                self.overlays.clear();
+                self.notifier.clear();
+                Response::ok()
+            }
+            Request::Reload => config::load(&self.config_path)
+                .and_then(|config| self.reload(config))
+                .into(),
+            Request::Show {
+                panel,
+                rows,
+                pixels,
+                duration_ms,
+            } => self.show(panel, &rows, &pixels, duration_ms).into(),
+            Request::Notify {
+                panel,
+                text,
+                icon,
+                priority,
+                duration_ms,
+            } => {
+                if panel >= self.mats.len() {
+                    return Response::error(format!(
+                        "panel {panel} does not exist, {} panel(s) detected",
+                        self.mats.len()
+                    ));
+                }
+                self.notifier.push(
+                    Notification {
+                        panel,
+                        text,
+                        icon,
+                        priority,
+                        duration: duration_ms.map(Duration::from_millis),
+                    },
+                    Instant::now(),
+                );
+                Response::ok()
+            }
+        }
+    }
+
+    fn show(
+        &mut self,
+        panel: usize,
+        rows: &[String],
+        pixels: &[u8],
+        duration_ms: u64,
+    ) -> Result<(), String> {
+        if panel >= self.mats.len() {
+            return Err(format!(
+                "panel {panel} does not exist, {} panel(s) detected",
+                self.mats.len()
+            ));
+        }
+
+        let frame = if pixels.is_empty() {
+            matrix::from_text_art(rows)?
+        } else {
+            matrix::from_pixels(pixels)?
+        };
+
+        self.overlays.retain(|overlay| overlay.panel != panel);
+        self.overlays.push(Overlay {
+            panel,
+            frame,
+            until: Instant::now() + Duration::from_millis(duration_ms),
+        });
+        Ok(())
+    }
+
+    /// Leave the panels in the state configured by `general.on_exit`.
+    pub fn shutdown(&mut self) -> Result<(), String> {
+        let on_exit = &self.config.general.on_exit;
+        log::info!("shutting down, panels: {:?}", on_exit.action);
+
+        for mat in self.mats.iter_mut() {
+            match on_exit.action {
+                ExitAction::Keep => {}
+                ExitAction::Clear => {
+                    mat.draw_matrix([[0; MATRIX_WIDTH]; MATRIX_HEIGHT])?;
+                }
+                ExitAction::Sleep => mat.sleep()?,
+                ExitAction::Pattern => {
+                    if self.asleep {
+                        mat.wake()?;
+                    }
+                    mat.draw_matrix(matrix::from_text_art(&on_exit.pattern)?)?;
+                }
+            }
+            mat.flush()?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,370 @@
+use std::{
+    sync::{mpsc::Sender, Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+use zbus::{
+    blocking::{object_server::InterfaceRef, Connection},
+    fdo, interface,
+    object_server::SignalEmitter,
+};
+
+use crate::{
+    config::{BusKind, DbusConfig},
+    ipc::{self, Command, Request, Response, Status},
+    notify::Priority,
+};
+
+const SERVICE_NAME: &str = "org.framework.LedMatrix";
+const OBJECT_PATH: &str = "/org/framework/LedMatrix";
+
+/// How often the daemon state is checked for changes worth a signal.
+const WATCH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How long a method call waits for the widget loop. Calls run on the zbus
+/// executor, so a busy loop must not stall every other call for long. A call
+/// that times out is cancelled and fails instead of running later.
+const CALL_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// The `org.framework.LedMatrix` interface. Calls are forwarded to the widget
+/// loop like control socket requests, properties are served from the status
+/// the watcher thread polls.
+struct LedMatrixService {
+    commands: Sender<Command>,
+    status: Arc<Mutex<Status>>,
+}
+
+impl LedMatrixService {
+    fn request(&self, request: Request) -> fdo::Result<Response> {
+        let response = ipc::dispatch_timeout(request, &self.commands, CALL_TIMEOUT);
+        if response.ok {
+            Ok(response)
+        } else {
+            Err(fdo::Error::Failed(
+                response
+                    .error
+                    .unwrap_or_else(|| "request failed".to_string()),
+            ))
+        }
+    }
+
+    /// Status from the last poll of the watcher thread.
+    fn status(&self) -> Status {
+        self.status
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .clone()
+    }
+}
+
+#[interface(name = "org.framework.LedMatrix")]
+impl LedMatrixService {
+    /// Show 306 row-major brightness values on a panel for a while.
+    fn draw_frame(&self, panel: u32, pixels: Vec<u8>, duration_ms: u32) -> fdo::Result<()> {
+        self.request(Request::Show {
+            panel: panel as usize,
+            rows: Vec::new(),
+            pixels,
+            duration_ms: if duration_ms == 0 {
+                ipc::DEFAULT_SHOW_DURATION_MS
+            } else {
+                duration_ms.into()
+            },
+        })?;
+        Ok(())
+    }
+
+    /// Show a message on a panel, 0 ms keeps it up for one scroll.
+    fn draw_text(&self, panel: u32, text: String, duration_ms: u32) -> fdo::Result<()> {
+        self.request(Request::Notify {
+            panel: panel as usize,
+            text,
+            icon: None,
+            priority: Priority::Normal,
+            duration_ms: (duration_ms > 0).then_some(duration_ms.into()),
+        })?;
+        Ok(())
+    }
+
+    /// Drop every manual override and temporary frame.
+    fn reset(&self) -> fdo::Result<()> {
+        self.request(Request::Reset)?;
+        Ok(())
+    }
+
+    #[zbus(property)]
+    fn brightness(&self) -> fdo::Result<u8> {
+        Ok(self.status().brightness.unwrap_or(0))
+    }
+
+    #[zbus(property)]
+    fn set_brightness(&self, value: u8) -> fdo::Result<()> {
+        self.request(Request::Brightness { value: Some(value) })?;
+        Ok(())
+    }
+
+    #[zbus(property)]
+    fn asleep(&self) -> fdo::Result<bool> {
+        Ok(self.status().asleep)
+    }
+
+    #[zbus(property)]
+    fn set_asleep(&self, value: bool) -> fdo::Result<()> {
+        self.request(if value { Request::Sleep } else { Request::Wake })?;
+        Ok(())
+    }
+
+    /// Active power profile, empty when none matches. Setting an empty name
+    /// returns to automatic selection.
+    #[zbus(property)]
+    fn profile(&self) -> fdo::Result<String> {
+        Ok(self.status().profile.unwrap_or_default())
+    }
+
+    #[zbus(property)]
+    fn set_profile(&self, value: String) -> fdo::Result<()> {
+        self.request(Request::Profile {
+            name: (!value.is_empty()).then_some(value),
+        })?;
+        Ok(())
+    }
+
+    /// Active layout, empty for the default widgets.
+    #[zbus(property)]
+    fn layout(&self) -> fdo::Result<String> {
+        Ok(self.status().layout.unwrap_or_default())
+    }
+
+    /// Index, serial port and firmware version of every connected panel.
+    #[zbus(property)]
+    fn panels(&self) -> fdo::Result<Vec<(u32, String, String)>> {
+        Ok(self
+            .status()
+            .panels
+            .into_iter()
+            .map(|panel| (panel.index as u32, panel.port, panel.firmware))
+            .collect())
+    }
+
+    #[zbus(signal)]
+    async fn panel_attached(
+        emitter: &SignalEmitter<'_>,
+        index: u32,
+        port: &str,
+    ) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    async fn panel_detached(
+        emitter: &SignalEmitter<'_>,
+        index: u32,
+        port: &str,
+    ) -> zbus::Result<()>;
+}
+
+fn connect(config: &DbusConfig) -> Result<zbus::blocking::connection::Builder<'static>, String> {
+    match (&config.bus_address, config.bus) {
+        (Some(address), _) => zbus::blocking::connection::Builder::address(address.as_str()),
+        (None, BusKind::Session) => zbus::blocking::connection::Builder::session(),
+        (None, BusKind::System) => zbus::blocking::connection::Builder::system(),
+    }
+    .map_err(|err| format!("unable to connect to D-Bus: {err}"))
+}
+
+/// Emit change signals for whatever differs between two daemon snapshots.
+fn announce(
+    iface: &InterfaceRef<LedMatrixService>,
+    old: &Status,
+    new: &Status,
+) -> zbus::Result<()> {
+    let emitter = iface.signal_emitter();
+    let service = iface.get();
+
+    futures_lite::future::block_on(async {
+        for panel in old.panels.iter() {
+            if !new.panels.iter().any(|p| p.port == panel.port) {
+                log::info!("panel {} detached", panel.port);
+                LedMatrixService::panel_detached(emitter, panel.index as u32, &panel.port).await?;
+            }
+        }
+        for panel in new.panels.iter() {
+            if !old.panels.iter().any(|p| p.port == panel.port) {
+                log::info!("panel {} attached", panel.port);
+                LedMatrixService::panel_attached(emitter, panel.index as u32, &panel.port).await?;
+            }
+        }
+
+        let ports = |status: &Status| -> Vec<String> {
+            status.panels.iter().map(|p| p.port.clone()).collect()
+        };
+        if ports(old) != ports(new) {
+            service.panels_changed(emitter).await?;
+        }
+        if old.brightness != new.brightness {
+            service.brightness_changed(emitter).await?;
+        }
+        if old.asleep != new.asleep {
+            service.asleep_changed(emitter).await?;
+        }
+        if old.profile != new.profile {
+            service.profile_changed(emitter).await?;
+        }
+        if old.layout != new.layout {
+            service.layout_changed(emitter).await?;
+        }
+        Ok(())
+    })
+}
+
+/// Register the service and keep its signals in sync with the daemon.
+pub fn spawn(config: &DbusConfig, commands: Sender<Command>) -> Result<Connection, String> {
+    let status = Arc::new(Mutex::new(Status::default()));
+    let conn = connect(config)?
+        .name(SERVICE_NAME)
+        .and_then(|builder| {
+            builder.serve_at(
+                OBJECT_PATH,
+                LedMatrixService {
+                    commands: commands.clone(),
+                    status: Arc::clone(&status),
+                },
+            )
+        })
+        .and_then(|builder| builder.build())
+        .map_err(|err| format!("unable to register {SERVICE_NAME}: {err}"))?;
+    let iface = conn
+        .object_server()
+        .interface::<_, LedMatrixService>(OBJECT_PATH)
+        .map_err(|err| format!("unable to find the served interface: {err}"))?;
+    log::info!("registered {SERVICE_NAME} on D-Bus");
+
+    thread::spawn(move || {
+        let mut last = Status::default();
+        loop {
+            // without panels the daemon answers with an error
+            let new = ipc::dispatch(Request::Status, &commands)
+                .status
+                .unwrap_or_default();
+            // the properties read the new state before their change signals go out
+            *status.lock().unwrap_or_else(|err| err.into_inner()) = new.clone();
+            if let Err(err) = announce(&iface, &last, &new) {
+                log::warn!("unable to emit D-Bus signals: {err}");
+            }
+            last = new;
+            thread::sleep(WATCH_INTERVAL);
+        }
+    });
+
+    Ok(conn)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+
+    use zbus::proxy::CacheProperties;
+
+    use super::*;
+    use crate::{ipc::PanelStatus, test_bus::TestBus};
+
+    /// Stand in for the widget loop: answer `Status` with a fixed snapshot,
+    /// refuse frames and pass every other request on to the test.
+    fn fake_loop() -> (Sender<Command>, mpsc::Receiver<Request>) {
+        let (commands, queue) = mpsc::channel::<Command>();
+        let (seen_tx, seen) = mpsc::channel();
+        thread::spawn(move || {
+            for command in queue {
+                command.answer(|request| match request {
+                    Request::Status => Response::status(Status {
+                        panels: vec![PanelStatus {
+                            index: 0,
+                            port: "/dev/ttyACM0".to_string(),
+                            firmware: "0.2.0".to_string(),
+                        }],
+                        brightness: Some(40),
+                        profile: Some("battery".to_string()),
+                        ..Status::default()
+                    }),
+                    Request::Show { .. } => Response::error("no panel 3"),
+                    request => {
+                        let _ = seen_tx.send(request);
+                        Response::ok()
+                    }
+                });
+            }
+        });
+        (commands, seen)
+    }
+
+    #[test]
+    fn serves_calls_and_properties_on_a_private_bus() {
+        let bus = TestBus::start("ledmatrix");
+        let (commands, seen) = fake_loop();
+        let _service = spawn(
+            &DbusConfig {
+                bus: BusKind::Session,
+                bus_address: Some(bus.address.clone()),
+            },
+            commands,
+        )
+        .unwrap();
+
+        let client = zbus::blocking::connection::Builder::address(bus.address.as_str())
+            .and_then(|builder| builder.build())
+            .unwrap();
+        let proxy = zbus::blocking::proxy::Builder::<zbus::blocking::Proxy>::new(&client)
+            .destination(SERVICE_NAME)
+            .and_then(|builder| builder.path(OBJECT_PATH))
+            .and_then(|builder| builder.interface(SERVICE_NAME))
+            .map(|builder| builder.cache_properties(CacheProperties::No))
+            .and_then(|builder| builder.build())
+            .unwrap();
+
+        // properties show up once the watcher polled the loop
+        let mut brightness = 0;
+        for _ in 0..50 {
+            brightness = proxy.get_property::<u8>("Brightness").unwrap();
+            if brightness != 0 {
+                break;
+            }
+            thread::sleep(Duration::from_millis(20));
+        }
+        assert_eq!(brightness, 40);
+        assert_eq!(proxy.get_property::<String>("Profile").unwrap(), "battery");
+        assert_eq!(proxy.get_property::<String>("Layout").unwrap(), "");
+        assert_eq!(
+            proxy
+                .get_property::<Vec<(u32, String, String)>>("Panels")
+                .unwrap(),
+            vec![(0, "/dev/ttyACM0".to_string(), "0.2.0".to_string())]
+        );
+
+        proxy.set_property("Brightness", 20u8).unwrap();
+        assert!(matches!(
+            seen.recv_timeout(Duration::from_secs(5)).unwrap(),
+            Request::Brightness { value: Some(20) }
+        ));
+
+        proxy.call_method("Reset", &()).unwrap();
+        assert!(matches!(
+            seen.recv_timeout(Duration::from_secs(5)).unwrap(),
+            Request::Reset
+        ));
+
+        proxy
+            .call_method("DrawText", &(1u32, "Hello", 0u32))
+            .unwrap();
+        let Request::Notify {
+            panel, duration_ms, ..
+        } = seen.recv_timeout(Duration::from_secs(5)).unwrap()
+        else {
+            panic!("expected a notification");
+        };
+        assert_eq!((panel, duration_ms), (1, None));
+
+        let err = proxy
+            .call_method("DrawFrame", &(3u32, vec![0u8; 306], 0u32))
+            .unwrap_err();
+        assert!(err.to_string().contains("no panel 3"), "{err}");
+    }
+}
@@ -0,0 +1,396 @@
+use std::{collections::HashMap, sync::mpsc::Sender, thread};
+
+use zbus::{
+    blocking::{fdo::MonitoringProxy, Connection, MessageIterator},
+    message::Type,
+    zvariant::OwnedValue,
+    MatchRule,
+};
+
+use crate::{
+    config::{DesktopNotificationsConfig, Urgency},
+    ipc::{Command, Request},
+    notify::Priority,
+};
+
+const NOTIFICATIONS_IFACE: &str = "org.freedesktop.Notifications";
+
+/// Arguments of `org.freedesktop.Notifications.Notify`.
+type NotifyArgs = (
+    String,
+    u32,
+    String,
+    String,
+    String,
+    Vec<String>,
+    HashMap<String, OwnedValue>,
+    i32,
+);
+
+/// A desktop notification reduced to what the panel can show.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DesktopNotification {
+    pub app_name: String,
+    pub summary: String,
+    pub body: String,
+    pub urgency: Urgency,
+    /// Sent with the `transient` hint, the server keeps no history of it.
+    pub transient: bool,
+}
+
+impl DesktopNotification {
+    fn from_args(args: NotifyArgs) -> Self {
+        let (app_name, _, _, summary, body, _, hints, _) = args;
+        let urgency = hints
+            .get("urgency")
+            .and_then(|value| u8::try_from(value).ok())
+            .map(|level| match level {
+                0 => Urgency::Low,
+                2 => Urgency::Critical,
+                _ => Urgency::Normal,
+            })
+            .unwrap_or(Urgency::Normal);
+        let transient = hints
+            .get("transient")
+            .and_then(|value| bool::try_from(value).ok())
+            .unwrap_or(false);
+
+        Self {
+            app_name,
+            summary,
+            body,
+            urgency,
+            transient,
+        }
+    }
+}
+
+/// Turns desktop notifications into panel notifications.
+pub struct Mirror {
+    config: DesktopNotificationsConfig,
+}
+
+impl Mirror {
+    pub fn new(config: &DesktopNotificationsConfig) -> Self {
+        Self {
+            config: config.clone(),
+        }
+    }
+
+    /// Whether the app, urgency and transient filters let `notification`
+    /// through.
+    pub fn accepts(&self, notification: &DesktopNotification) -> bool {
+        let app = notification.app_name.as_str();
+        let allowed = self.config.allow_apps.is_empty()
+            || self
+                .config
+                .allow_apps
+                .iter()
+                .any(|name| name.eq_ignore_ascii_case(app));
+        let denied = self
+            .config
+            .deny_apps
+            .iter()
+            .any(|name| name.eq_ignore_ascii_case(app));
+
+        allowed
+            && !denied
+            && notification.urgency >= self.config.min_urgency
+            && (self.config.transient || !notification.transient)
+    }
+
+    /// Panel request for an accepted notification.
+    pub fn to_request(&self, notification: &DesktopNotification) -> Request {
+        let mut text = notification.summary.clone();
+        if self.config.show_body && !notification.body.is_empty() {
+            text.push_str(": ");
+            text.push_str(&notification.body);
+        }
+        let text: String = text
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+            .chars()
+            .take(self.config.max_length)
+            .collect();
+
+        let icon = self
+            .config
+            .icons
+            .iter()
+            .find(|(app, _)| app.eq_ignore_ascii_case(&notification.app_name))
+            .map(|(_, icon)| *icon);
+
+        Request::Notify {
+            panel: self.config.panel,
+            text,
+            icon,
+            priority: match notification.urgency {
+                Urgency::Low => Priority::Low,
+                Urgency::Normal => Priority::Normal,
+                Urgency::Critical => Priority::Urgent,
+            },
+            duration_ms: None,
+        }
+    }
+}
+
+fn connect(config: &DesktopNotificationsConfig) -> Result<Connection, String> {
+    match &config.bus_address {
+        Some(address) => zbus::blocking::connection::Builder::address(address.as_str())
+            .and_then(|builder| builder.build()),
+        None => Connection::session(),
+    }
+    .map_err(|err| format!("unable to connect to the session bus: {err}"))
+}
+
+fn monitor(conn: Connection) -> Result<MessageIterator, String> {
+    let rule = MatchRule::builder()
+        .msg_type(Type::MethodCall)
+        .interface(NOTIFICATIONS_IFACE)
+        .and_then(|builder| builder.member("Notify"))
+        .map_err(|err| format!("invalid match rule: {err}"))?
+        .build();
+
+    MonitoringProxy::new(&conn)
+        .map_err(|err| format!("unable to monitor the session bus: {err}"))?
+        .become_monitor(&[rule], 0)
+        .map_err(|err| format!("unable to monitor the session bus: {err}"))?;
+
+    Ok(MessageIterator::from(conn))
+}
+
+/// Watch the session bus and forward accepted notifications to the daemon.
+pub fn spawn(config: &DesktopNotificationsConfig, commands: Sender<Command>) -> Result<(), String> {
+    let messages = monitor(connect(config)?)?;
+    let mirror = Mirror::new(config);
+    log::info!("mirroring desktop notifications to panel {}", config.panel);
+
+    thread::spawn(move || {
+        for message in messages {
+            let Ok(message) = message else {
+                continue;
+            };
+            let header = message.header();
+            if header.message_type() != Type::MethodCall
+                || header
+                    .member()
+                    .is_none_or(|member| member.as_str() != "Notify")
+            {
+                continue;
+            }
+
+            let Ok(args) = message.body().deserialize::<NotifyArgs>() else {
+                continue;
+            };
+            let notification = DesktopNotification::from_args(args);
+            if !mirror.accepts(&notification) {
+                log::debug!("ignoring notification from {}", notification.app_name);
+                continue;
+            }
+
+            log::debug!("mirroring notification from {}", notification.app_name);
+            // nobody waits for the answer
+            let (command, _) = Command::new(mirror.to_request(&notification));
+            if commands.send(command).is_err() {
+                break;
+            }
+        }
+        log::warn!("stopped mirroring desktop notifications");
+    });
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::mpsc, time::Duration};
+
+    use zbus::{interface, zvariant::Value};
+
+    use super::*;
+    use crate::{notify::Icon, test_bus::TestBus};
+
+    fn config() -> DesktopNotificationsConfig {
+        toml::from_str("").unwrap()
+    }
+
+    fn notification(app_name: &str, urgency: Urgency) -> DesktopNotification {
+        DesktopNotification {
+            app_name: app_name.to_string(),
+            summary: "New mail".to_string(),
+            body: "From  Alice\nHello there".to_string(),
+            urgency,
+            transient: false,
+        }
+    }
+
+    /// Text and priority of a notify request.
+    fn notify_fields(request: Request) -> (String, Option<Icon>, Priority) {
+        match request {
+            Request::Notify {
+                text,
+                icon,
+                priority,
+                ..
+            } => (text, icon, priority),
+            other => panic!("expected a notify request, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn app_filters_ignore_case() {
+        let mirror = Mirror::new(&DesktopNotificationsConfig {
+            allow_apps: vec!["thunderbird".to_string(), "Slack".to_string()],
+            deny_apps: vec!["slack".to_string()],
+            ..config()
+        });
+        assert!(mirror.accepts(&notification("Thunderbird", Urgency::Normal)));
+        assert!(!mirror.accepts(&notification("Slack", Urgency::Normal)));
+        assert!(!mirror.accepts(&notification("Spotify", Urgency::Normal)));
+    }
+
+    #[test]
+    fn urgency_threshold() {
+        let mirror = Mirror::new(&DesktopNotificationsConfig {
+            min_urgency: Urgency::Normal,
+            ..config()
+        });
+        assert!(!mirror.accepts(&notification("app", Urgency::Low)));
+        assert!(mirror.accepts(&notification("app", Urgency::Normal)));
+        assert!(mirror.accepts(&notification("app", Urgency::Critical)));
+    }
+
+    #[test]
+    fn transient_only_when_enabled() {
+        let transient = DesktopNotification {
+            transient: true,
+            ..notification("volume", Urgency::Normal)
+        };
+        assert!(!Mirror::new(&config()).accepts(&transient));
+        let mirror = Mirror::new(&DesktopNotificationsConfig {
+            transient: true,
+            ..config()
+        });
+        assert!(mirror.accepts(&transient));
+    }
+
+    #[test]
+    fn request_priority_and_icon() {
+        let mirror = Mirror::new(&DesktopNotificationsConfig {
+            panel: 1,
+            icons: HashMap::from([("Thunderbird".to_string(), Icon::Mail)]),
+            ..config()
+        });
+        for (urgency, priority) in [
+            (Urgency::Low, Priority::Low),
+            (Urgency::Normal, Priority::Normal),
+            (Urgency::Critical, Priority::Urgent),
+        ] {
+            let (_, _, mapped) = notify_fields(mirror.to_request(&notification("app", urgency)));
+            assert_eq!(mapped, priority);
+        }
+
+        let request = mirror.to_request(&notification("thunderbird", Urgency::Normal));
+        let Request::Notify { panel, .. } = request else {
+            unreachable!()
+        };
+        assert_eq!(panel, 1);
+        let (text, icon, _) = notify_fields(request);
+        assert_eq!(text, "New mail");
+        assert_eq!(icon, Some(Icon::Mail));
+        let (_, icon, _) = notify_fields(mirror.to_request(&notification("app", Urgency::Normal)));
+        assert_eq!(icon, None);
+    }
+
+    #[test]
+    fn body_collapsed_and_truncated() {
+        let mirror = Mirror::new(&DesktopNotificationsConfig {
+            show_body: true,
+            max_length: 20,
+            ..config()
+        });
+        let (text, _, _) = notify_fields(mirror.to_request(&notification("app", Urgency::Normal)));
+        assert_eq!(text, "New mail: From Alice");
+    }
+
+    /// Accepts every call like a notification server would.
+    struct StubNotifications;
+
+    #[interface(name = "org.freedesktop.Notifications")]
+    impl StubNotifications {
+        #[allow(clippy::too_many_arguments)]
+        fn notify(
+            &self,
+            _app_name: String,
+            _replaces_id: u32,
+            _app_icon: String,
+            _summary: String,
+            _body: String,
+            _actions: Vec<String>,
+            _hints: HashMap<String, OwnedValue>,
+            _expire_timeout: i32,
+        ) -> u32 {
+            1
+        }
+    }
+
+    #[test]
+    fn mirrors_notify_calls_on_a_private_bus() {
+        let bus = TestBus::start("notify");
+        let config = DesktopNotificationsConfig {
+            bus_address: Some(bus.address.clone()),
+            deny_apps: vec!["slack".to_string()],
+            ..config()
+        };
+        let (commands, requests) = mpsc::channel();
+        spawn(&config, commands).unwrap();
+
+        let _server = zbus::blocking::connection::Builder::address(bus.address.as_str())
+            .and_then(|builder| builder.name(NOTIFICATIONS_IFACE))
+            .and_then(|builder| {
+                builder.serve_at("/org/freedesktop/Notifications", StubNotifications)
+            })
+            .and_then(|builder| builder.build())
+            .unwrap();
+        let client = zbus::blocking::connection::Builder::address(bus.address.as_str())
+            .and_then(|builder| builder.build())
+            .unwrap();
+        for (app_name, summary, urgency, transient) in [
+            ("deja-dup", "Backup done", 0u8, false),
+            ("pavucontrol", "Volume 40%", 2, true),
+            ("slack", "New message", 2, false),
+            ("upower", "Battery low", 2, false),
+        ] {
+            let hints = HashMap::from([
+                ("urgency", Value::from(urgency)),
+                ("transient", Value::from(transient)),
+            ]);
+            client
+                .call_method(
+                    Some(NOTIFICATIONS_IFACE),
+                    "/org/freedesktop/Notifications",
+                    Some(NOTIFICATIONS_IFACE),
+                    "Notify",
+                    &(
+                        app_name,
+                        0u32,
+                        "",
+                        summary,
+                        "",
+                        Vec::<&str>::new(),
+                        hints,
+                        -1i32,
+                    ),
+                )
+                .unwrap();
+        }
+
+        // the low urgency, transient and denied notifications are filtered out
+        let command: Command = requests.recv_timeout(Duration::from_secs(5)).unwrap();
+        let (text, _, priority) = notify_fields(command.request);
+        assert_eq!(text, "Battery low");
+        assert_eq!(priority, Priority::Urgent);
+        assert!(requests.recv_timeout(Duration::from_millis(100)).is_err());
+    }
+}
@@ -0,0 +1,208 @@
+use crate::{
+    config::GammaConfig,
+    matrix::{Matrix, MATRIX_HEIGHT, MATRIX_WIDTH},
+    widget::ON_FULL,
+};
+
+/// Lookup table translating perceptual widget levels into LED PWM values.
+///
+/// Widgets draw in a perceptual brightness space where 0 is off and 255 is
+/// the brightest level, and where equal steps should look equally far apart.
+/// The table is applied once per frame right before the serial write.
+#[derive(Clone)]
+pub struct GammaTable {
+    lut: [u8; 256],
+}
+
+impl GammaTable {
+    /// Pass levels through unchanged.
+    pub fn identity() -> Self {
+        let mut lut = [0; 256];
+        for (idx, out) in lut.iter_mut().enumerate() {
+            *out = idx as u8;
+        }
+        Self { lut }
+    }
+
+    /// Build a power-law curve where `out = 255 * (in / 255) ^ exponent`.
+    pub fn from_exponent(exponent: f32) -> Self {
+        debug_assert!(
+            exponent.is_finite() && exponent > 0.0,
+            "from_config rejects non-positive gamma exponents"
+        );
+
+        let mut lut = [0; 256];
+        for (idx, out) in lut.iter_mut().enumerate() {
+            let level = idx as f32 / 255.0;
+            *out = (level.powf(exponent) * 255.0).round().clamp(0.0, 255.0) as u8;
+        }
+        Self { lut }
+    }
+
+    /// Build a curve by linearly interpolating evenly spaced control points.
+    ///
+    /// The first point maps level 0 and the last point maps level 255, so a
+    /// 256 entry list is used verbatim. Points must not decrease, a brighter
+    /// level never comes out dimmer.
+    pub fn from_points(points: &[u8]) -> Result<Self, String> {
+        if points.len() < 2 {
+            return Err(format!(
+                "gamma lookup table needs at least 2 points, got {}",
+                points.len()
+            ));
+        }
+        if let Some(idx) = points.windows(2).position(|pair| pair[0] > pair[1]) {
+            return Err(format!(
+                "gamma lookup table points must not decrease, {} follows {}",
+                points[idx + 1],
+                points[idx]
+            ));
+        }
+
+        let segments = (points.len() - 1) as f32;
+        let mut lut = [0; 256];
+        for (idx, out) in lut.iter_mut().enumerate() {
+            let pos = idx as f32 / 255.0 * segments;
+            let lower = (pos.floor() as usize).min(points.len() - 2);
+            let fract = pos - lower as f32;
+            let value =
+                points[lower] as f32 + (points[lower + 1] as f32 - points[lower] as f32) * fract;
+            *out = value.round().clamp(0.0, 255.0) as u8;
+        }
+        Ok(Self { lut })
+    }
+
+    /// Build the table described by the `[general.gamma]` config section.
+    pub fn from_config(config: &GammaConfig) -> Result<Self, String> {
+        if !config.exponent.is_finite() || config.exponent <= 0.0 {
+            return Err(format!(
+                "gamma exponent must be a positive number, got {}",
+                config.exponent
+            ));
+        }
+        if config.lut.is_empty() {
+            Ok(Self::from_exponent(config.exponent))
+        } else {
+            Self::from_points(&config.lut)
+        }
+    }
+
+    /// Map one perceptual level to its PWM value.
+    pub fn map(&self, level: u8) -> u8 {
+        self.lut[level as usize]
+    }
+
+    /// Map every LED of a composed panel matrix.
+    pub fn apply(&self, mat: Matrix) -> Matrix {
+        let mut out = mat;
+        for row in out.iter_mut() {
+            for cell in row.iter_mut() {
+                *cell = self.map(*cell);
+            }
+        }
+        out
+    }
+}
+
+impl Default for GammaTable {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+/// Test pattern used to tune the gamma curve on real hardware.
+///
+/// The left four columns ramp through the whole perceptual range from top to
+/// bottom. The right four columns ramp from off to `ON_FULL`, which is the
+/// range widgets actually draw in, so partial bar cells can be compared.
+pub fn calibration_pattern() -> Matrix {
+    let mut mat = [[0; MATRIX_WIDTH]; MATRIX_HEIGHT];
+    let steps = (MATRIX_HEIGHT - 1) as f32;
+
+    for (row, cells) in mat.iter_mut().enumerate() {
+        let full_range = (255.0 * row as f32 / steps).round() as u8;
+        let widget_range = (ON_FULL as f32 * row as f32 / steps).round() as u8;
+        for (col, cell) in cells.iter_mut().enumerate() {
+            *cell = match col {
+                0..=3 => full_range,
+                4 => 0,
+                _ => widget_range,
+            };
+        }
+    }
+
+    mat
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(gamma: &GammaTable) -> Vec<u8> {
+        (0..=255).map(|level| gamma.map(level)).collect()
+    }
+
+    #[test]
+    fn exponent_curve() {
+        assert_eq!(
+            table(&GammaTable::from_exponent(1.0)),
+            table(&GammaTable::identity())
+        );
+
+        let gamma = GammaTable::from_exponent(2.0);
+        assert_eq!((gamma.map(0), gamma.map(255)), (0, 255));
+        // (128 / 255)^2 * 255 = 64.25
+        assert_eq!(gamma.map(128), 64);
+        assert!(table(&gamma).windows(2).all(|pair| pair[0] <= pair[1]));
+        assert!((1..255).all(|level| gamma.map(level) <= level));
+
+        let gamma = GammaTable::from_exponent(0.5);
+        assert!((1..255).all(|level| gamma.map(level) >= level));
+    }
+
+    #[test]
+    fn points_are_interpolated() {
+        let gamma = GammaTable::from_points(&[0, 100, 255]).unwrap();
+        assert_eq!((gamma.map(0), gamma.map(255)), (0, 255));
+        // the middle point sits at level 127.5, halfway along each segment
+        // lies halfway between its ends
+        assert_eq!(gamma.map(64), 50);
+        assert_eq!((gamma.map(127), gamma.map(128)), (100, 101));
+        assert_eq!(gamma.map(191), 177);
+        assert!(table(&gamma).windows(2).all(|pair| pair[0] <= pair[1]));
+
+        let flat = GammaTable::from_points(&[10, 10]).unwrap();
+        assert!(table(&flat).iter().all(|&out| out == 10));
+
+        let lut: Vec<u8> = (0..=255).rev().map(|level: u8| 255 - level).collect();
+        assert_eq!(table(&GammaTable::from_points(&lut).unwrap()), lut);
+    }
+
+    #[test]
+    fn invalid_configs_are_rejected() {
+        let config = |exponent: f32, lut: &[u8]| GammaConfig {
+            exponent,
+            lut: lut.to_vec(),
+        };
+        assert!(GammaTable::from_config(&config(2.2, &[])).is_ok());
+        assert!(GammaTable::from_config(&config(1.0, &[0, 60, 255])).is_ok());
+
+        for (exponent, lut) in [
+            (0.0, &[][..]),
+            (-1.0, &[]),
+            (f32::NAN, &[]),
+            (1.0, &[128]),
+            (1.0, &[0, 200, 100, 255]),
+            (1.0, &[255, 0]),
+        ] {
+            assert!(
+                GammaTable::from_config(&config(exponent, lut)).is_err(),
+                "{exponent} {lut:?}"
+            );
+        }
+
+        // points outside 0..=255 don't make it past the config parser
+        assert!(toml::from_str::<GammaConfig>("lut = [0, 128, 300]").is_err());
+        assert!(toml::from_str::<GammaConfig>("lut = [-1, 255]").is_err());
+    }
+}
@@ -0,0 +1,311 @@
+use std::time::{Duration, Instant};
+
+use futures_lite::{future, StreamExt};
+use zbus::{
+    blocking::{proxy::Builder, Connection, Proxy},
+    proxy::{CacheProperties, SignalStream},
+    zvariant::OwnedObjectPath,
+};
+
+use crate::config::IdleConfig;
+
+const LOGIND_DEST: &str = "org.freedesktop.login1";
+const LOGIND_PATH: &str = "/org/freedesktop/login1";
+const MANAGER_IFACE: &str = "org.freedesktop.login1.Manager";
+const SEAT_IFACE: &str = "org.freedesktop.login1.Seat";
+const SESSION_IFACE: &str = "org.freedesktop.login1.Session";
+
+/// How often logind is queried. Locking or closing the lid doesn't need to
+/// be noticed within one frame.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Session state that decides whether the panels should stay lit.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SessionState {
+    pub locked: bool,
+    pub idle: bool,
+    pub lid_closed: bool,
+    /// Between `PrepareForSleep(true)` and `PrepareForSleep(false)`.
+    pub suspending: bool,
+}
+
+/// Reads the session state of the active session on a seat from logind.
+pub struct LogindSource {
+    conn: Connection,
+    seat: String,
+    /// `PrepareForSleep` signals of the manager, drained on every read.
+    sleep_signals: SignalStream<'static>,
+    suspending: bool,
+}
+
+impl LogindSource {
+    /// Connect to the bus named in the config, the system bus by default.
+    pub fn connect(config: &IdleConfig) -> Result<Self, String> {
+        let conn = match &config.bus_address {
+            Some(address) => zbus::blocking::connection::Builder::address(address.as_str())
+                .and_then(|builder| builder.build()),
+            None => Connection::system(),
+        }
+        .map_err(|err| format!("unable to connect to logind bus: {err}"))?;
+
+        Self::with_connection(conn, &config.seat)
+    }
+
+    /// Use an existing connection, e.g. to a private bus serving a mock logind.
+    pub fn with_connection(conn: Connection, seat: &str) -> Result<Self, String> {
+        let sleep_signals = Self::proxy_on(&conn, LOGIND_PATH, MANAGER_IFACE)
+            .and_then(|manager| future::block_on(manager.inner().receive_signal("PrepareForSleep")))
+            .map_err(|err| format!("unable to watch logind for suspend: {err}"))?;
+        Ok(Self {
+            conn,
+            seat: seat.to_string(),
+            sleep_signals,
+            suspending: false,
+        })
+    }
+
+    fn proxy<'a>(&self, path: &'a str, iface: &'a str) -> zbus::Result<Proxy<'a>> {
+        Self::proxy_on(&self.conn, path, iface)
+    }
+
+    fn proxy_on<'a>(conn: &Connection, path: &'a str, iface: &'a str) -> zbus::Result<Proxy<'a>> {
+        Builder::<Proxy>::new(conn)
+            .destination(LOGIND_DEST)?
+            .path(path)?
+            .interface(iface)?
+            .cache_properties(CacheProperties::No)
+            .build()
+    }
+
+    /// Latest `PrepareForSleep` argument among the signals received so far.
+    fn drain_sleep_signals(&mut self) {
+        while let Some(Some(message)) =
+            future::block_on(future::poll_once(self.sleep_signals.next()))
+        {
+            if let Ok(start) = message.body().deserialize::<bool>() {
+                self.suspending = start;
+            }
+        }
+    }
+
+    pub fn read(&mut self) -> Result<SessionState, String> {
+        let map_err = |err: zbus::Error| format!("logind query failed: {err}");
+        self.drain_sleep_signals();
+
+        let manager = self.proxy(LOGIND_PATH, MANAGER_IFACE).map_err(map_err)?;
+        let lid_closed = manager.get_property::<bool>("LidClosed").map_err(map_err)?;
+
+        let seat_path = format!("{LOGIND_PATH}/seat/{}", self.seat);
+        let seat = self.proxy(&seat_path, SEAT_IFACE).map_err(map_err)?;
+        let (session_id, session_path) = seat
+            .get_property::<(String, OwnedObjectPath)>("ActiveSession")
+            .map_err(map_err)?;
+
+        // no one is logged in on the seat, only the lid matters
+        if session_id.is_empty() {
+            return Ok(SessionState {
+                lid_closed,
+                suspending: self.suspending,
+                ..SessionState::default()
+            });
+        }
+
+        let session = self
+            .proxy(session_path.as_str(), SESSION_IFACE)
+            .map_err(map_err)?;
+        Ok(SessionState {
+            locked: session
+                .get_property::<bool>("LockedHint")
+                .map_err(map_err)?,
+            idle: session.get_property::<bool>("IdleHint").map_err(map_err)?,
+            lid_closed,
+            suspending: self.suspending,
+        })
+    }
+}
+
+/// Decides whether the panels should sleep based on the logind session.
+pub struct IdleMonitor {
+    config: IdleConfig,
+    source: Option<LogindSource>,
+    state: SessionState,
+    last_poll: Option<Instant>,
+    failing: bool,
+}
+
+impl IdleMonitor {
+    pub fn new(config: &IdleConfig) -> Self {
+        Self {
+            config: config.clone(),
+            source: None,
+            state: SessionState::default(),
+            last_poll: None,
+            failing: false,
+        }
+    }
+
+    fn poll(&mut self) -> Result<SessionState, String> {
+        if self.source.is_none() {
+            self.source = Some(LogindSource::connect(&self.config)?);
+        }
+        let result = self
+            .source
+            .as_mut()
+            .expect("source was connected above")
+            .read();
+        if result.is_err() {
+            // reconnect next time in case logind restarted
+            self.source = None;
+        }
+        result
+    }
+
+    /// Current session state, refreshed at most every `POLL_INTERVAL`.
+    pub fn state(&mut self) -> SessionState {
+        if self
+            .last_poll
+            .is_none_or(|last| last.elapsed() >= POLL_INTERVAL)
+        {
+            self.last_poll = Some(Instant::now());
+            match self.poll() {
+                Ok(state) => {
+                    if state != self.state {
+                        log::debug!("session state changed: {state:?}");
+                    }
+                    self.state = state;
+                    self.failing = false;
+                }
+                Err(err) => {
+                    if !self.failing {
+                        log::warn!("session idle detection unavailable: {err}");
+                        self.failing = true;
+                    }
+                    // never keep the panels dark because logind went away
+                    self.state = SessionState::default();
+                }
+            }
+        }
+        self.state
+    }
+
+    /// Whether the configured conditions say the panels should be asleep.
+    /// They always sleep while the system suspends.
+    pub fn should_sleep(&mut self) -> bool {
+        let state = self.state();
+        state.suspending
+            || (self.config.when_locked && state.locked)
+            || (self.config.when_idle && state.idle)
+            || (self.config.when_lid_closed && state.lid_closed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use zbus::{interface, object_server::SignalEmitter};
+
+    use super::*;
+    use crate::test_bus::TestBus;
+
+    const SESSION_PATH: &str = "/org/freedesktop/login1/session/c1";
+
+    struct StubManager;
+
+    #[interface(name = "org.freedesktop.login1.Manager")]
+    impl StubManager {
+        #[zbus(property)]
+        fn lid_closed(&self) -> bool {
+            false
+        }
+
+        #[zbus(signal)]
+        async fn prepare_for_sleep(emitter: &SignalEmitter<'_>, start: bool) -> zbus::Result<()>;
+    }
+
+    struct StubSeat;
+
+    #[interface(name = "org.freedesktop.login1.Seat")]
+    impl StubSeat {
+        #[zbus(property)]
+        fn active_session(&self) -> (String, OwnedObjectPath) {
+            (
+                "c1".to_string(),
+                OwnedObjectPath::try_from(SESSION_PATH).unwrap(),
+            )
+        }
+    }
+
+    struct StubSession {
+        idle: bool,
+    }
+
+    #[interface(name = "org.freedesktop.login1.Session")]
+    impl StubSession {
+        #[zbus(property)]
+        fn locked_hint(&self) -> bool {
+            false
+        }
+
+        #[zbus(property)]
+        fn idle_hint(&self) -> bool {
+            self.idle
+        }
+    }
+
+    /// Poll until the monitor reaches `asleep`, signals take a moment to
+    /// arrive.
+    fn wait_for(monitor: &mut IdleMonitor, asleep: bool) {
+        let start = Instant::now();
+        loop {
+            monitor.last_poll = None;
+            let sleeping = monitor.should_sleep();
+            // a failed query also keeps the panels awake
+            assert!(!monitor.failing, "logind stub query failed");
+            if sleeping == asleep {
+                return;
+            }
+            assert!(
+                start.elapsed() < Duration::from_secs(5),
+                "panels never {}",
+                if asleep { "slept" } else { "woke up" }
+            );
+            std::thread::sleep(Duration::from_millis(20));
+        }
+    }
+
+    #[test]
+    fn sleeps_while_idle_or_suspending() {
+        let bus = TestBus::start("logind");
+        let logind = zbus::blocking::connection::Builder::address(bus.address.as_str())
+            .and_then(|builder| builder.name(LOGIND_DEST))
+            .and_then(|builder| builder.serve_at(LOGIND_PATH, StubManager))
+            .and_then(|builder| builder.serve_at("/org/freedesktop/login1/seat/seat0", StubSeat))
+            .and_then(|builder| builder.serve_at(SESSION_PATH, StubSession { idle: false }))
+            .and_then(|builder| builder.build())
+            .unwrap();
+        let server = logind.object_server();
+        let session = server.interface::<_, StubSession>(SESSION_PATH).unwrap();
+        let manager = server.interface::<_, StubManager>(LOGIND_PATH).unwrap();
+
+        let config: IdleConfig =
+            toml::from_str(&format!("bus_address = \"{}\"", bus.address)).unwrap();
+        let mut monitor = IdleMonitor::new(&config);
+        wait_for(&mut monitor, false);
+
+        session.get_mut().idle = true;
+        wait_for(&mut monitor, true);
+        session.get_mut().idle = false;
+        wait_for(&mut monitor, false);
+
+        let suspend = |start| {
+            future::block_on(StubManager::prepare_for_sleep(
+                manager.signal_emitter(),
+                start,
+            ))
+            .unwrap()
+        };
+        suspend(true);
+        wait_for(&mut monitor, true);
+        suspend(false);
+        wait_for(&mut monitor, false);
+    }
+}
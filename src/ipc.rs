@@ -0,0 +1,394 @@
+use std::{
+    io::{BufRead, BufReader, Write},
+    os::unix::net::{UnixListener, UnixStream},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, RecvTimeoutError, Sender},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::notify::{Icon, Priority};
+
+/// File name of the control socket inside the runtime directory.
+const SOCKET_NAME: &str = "framework-led-widgets.sock";
+
+/// How long a client waits for the widget loop to answer.
+const REPLY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long a frame sent without a duration stays up.
+pub const DEFAULT_SHOW_DURATION_MS: u64 = 5000;
+
+/// A request received on the socket together with the channel to answer on.
+pub struct Command {
+    pub request: Request,
+    reply: Sender<Response>,
+    /// Set by whichever side gets to it first: the loop starting on the
+    /// request or the sender giving up on the answer.
+    claimed: Arc<AtomicBool>,
+}
+
+impl Command {
+    /// Wrap `request`, the receiver gets the answer.
+    pub fn new(request: Request) -> (Self, Receiver<Response>) {
+        let (reply, reply_rx) = mpsc::channel();
+        let command = Self {
+            request,
+            reply,
+            claimed: Arc::new(AtomicBool::new(false)),
+        };
+        (command, reply_rx)
+    }
+
+    /// Answer the request with `handle`. Returns `false` without running it
+    /// when the sender already timed out, so a late request has no effect.
+    pub fn answer(self, handle: impl FnOnce(Request) -> Response) -> bool {
+        if self.claimed.swap(true, Ordering::SeqCst) {
+            return false;
+        }
+        let _ = self.reply.send(handle(self.request));
+        true
+    }
+}
+
+/// One line of the control protocol sent by a client.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "cmd", rename_all = "snake_case")]
+pub enum Request {
+    /// Report panels, widgets and the current display state.
+    Status,
+    /// Force a panel brightness, `null` returns to the automatic value.
+    Brightness {
+        value: Option<u8>,
+    },
+    Sleep,
+    Wake,
+    /// Force a power profile by name, `null` returns to automatic selection.
+    Profile {
+        name: Option<String>,
+    },
+    /// Drop every manual override and temporary frame.
+    Reset,
+    /// Load the config file again.
+    Reload,
+    /// Show a frame on one panel for a while instead of its widgets.
+    Show {
+        panel: usize,
+        /// Frame as text art, see `matrix::from_text_art`.
+        #[serde(default)]
+        rows: Vec<String>,
+        /// Frame as 306 row-major brightness values.
+        #[serde(default)]
+        pixels: Vec<u8>,
+        #[serde(default = "default_show_duration")]
+        duration_ms: u64,
+    },
+    /// Show a message on one panel above its widgets.
+    Notify {
+        #[serde(default)]
+        panel: usize,
+        text: String,
+        #[serde(default)]
+        icon: Option<Icon>,
+        #[serde(default)]
+        priority: Priority,
+        /// Defaults to the time the text needs to scroll by once.
+        #[serde(default)]
+        duration_ms: Option<u64>,
+    },
+}
+
+fn default_show_duration() -> u64 {
+    DEFAULT_SHOW_DURATION_MS
+}
+
+/// Answer to one request.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Response {
+    pub ok: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<Status>,
+}
+
+impl Response {
+    pub fn ok() -> Self {
+        Self {
+            ok: true,
+            ..Self::default()
+        }
+    }
+
+    pub fn error(message: impl Into<String>) -> Self {
+        Self {
+            ok: false,
+            error: Some(message.into()),
+            ..Self::default()
+        }
+    }
+
+    pub fn status(status: Status) -> Self {
+        Self {
+            ok: true,
+            status: Some(status),
+            ..Self::default()
+        }
+    }
+}
+
+impl From<Result<(), String>> for Response {
+    fn from(result: Result<(), String>) -> Self {
+        match result {
+            Ok(()) => Response::ok(),
+            Err(err) => Response::error(err),
+        }
+    }
+}
+
+/// Snapshot of the running daemon.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Status {
+    pub panels: Vec<PanelStatus>,
+    pub widgets: Vec<WidgetStatus>,
+    pub fps: f32,
+    pub brightness: Option<u8>,
+    pub asleep: bool,
+    pub profile: Option<String>,
+    pub layout: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PanelStatus {
+    pub index: usize,
+    pub port: String,
+    pub firmware: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WidgetStatus {
+    pub panel: usize,
+    pub x: usize,
+    pub y: usize,
+    pub kind: String,
+}
+
+/// Socket path used when neither the config nor the command line name one.
+pub fn default_socket_path() -> PathBuf {
+    let dir = std::env::var_os("XDG_RUNTIME_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("/tmp"));
+    dir.join(SOCKET_NAME)
+}
+
+/// Listening control socket, removed again when dropped.
+pub struct Server {
+    path: PathBuf,
+}
+
+impl Server {
+    /// Bind the socket and forward every request to `commands`.
+    pub fn start(path: impl AsRef<Path>, commands: Sender<Command>) -> Result<Self, String> {
+        let path = path.as_ref().to_path_buf();
+
+        // a socket file nobody listens on is left over from a crash
+        if path.exists() {
+            if UnixStream::connect(&path).is_ok() {
+                return Err(format!(
+                    "control socket {} is already in use",
+                    path.display()
+                ));
+            }
+            std::fs::remove_file(&path)
+                .map_err(|err| format!("unable to remove stale {}: {err}", path.display()))?;
+        }
+
+        let listener = UnixListener::bind(&path)
+            .map_err(|err| format!("unable to bind {}: {err}", path.display()))?;
+        log::info!("listening for control commands on {}", path.display());
+
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                match stream {
+                    Ok(stream) => {
+                        let commands = commands.clone();
+                        thread::spawn(move || serve_client(stream, commands));
+                    }
+                    Err(err) => log::warn!("control socket accept failed: {err}"),
+                }
+            }
+        });
+
+        Ok(Self { path })
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+fn serve_client(stream: UnixStream, commands: Sender<Command>) {
+    let Ok(mut writer) = stream.try_clone() else {
+        return;
+    };
+
+    for line in BufReader::new(stream).lines() {
+        let Ok(line) = line else {
+            return;
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let response = match serde_json::from_str::<Request>(&line) {
+            Ok(request) => dispatch(request, &commands),
+            Err(err) => Response::error(format!("invalid request: {err}")),
+        };
+
+        let Ok(mut encoded) = serde_json::to_string(&response) else {
+            return;
+        };
+        encoded.push('\n');
+        if writer.write_all(encoded.as_bytes()).is_err() {
+            return;
+        }
+    }
+}
+
+/// Hand `request` to the widget loop and wait for its answer.
+pub fn dispatch(request: Request, commands: &Sender<Command>) -> Response {
+    dispatch_timeout(request, commands, REPLY_TIMEOUT)
+}
+
+/// Like `dispatch`, giving up after `timeout`. A request the loop has not
+/// started on by then is cancelled, one it is already handling is waited for.
+pub fn dispatch_timeout(
+    request: Request,
+    commands: &Sender<Command>,
+    timeout: Duration,
+) -> Response {
+    let (command, reply) = Command::new(request);
+    let claimed = Arc::clone(&command.claimed);
+    if commands.send(command).is_err() {
+        return Response::error("daemon is shutting down");
+    }
+    match reply.recv_timeout(timeout) {
+        Ok(response) => response,
+        Err(RecvTimeoutError::Timeout) if !claimed.swap(true, Ordering::SeqCst) => {
+            Response::error("daemon did not answer in time, request dropped")
+        }
+        Err(RecvTimeoutError::Timeout) => reply
+            .recv()
+            .unwrap_or_else(|_| Response::error("daemon is shutting down")),
+        Err(RecvTimeoutError::Disconnected) => Response::error("daemon is shutting down"),
+    }
+}
+
+/// Send one request to a running daemon and wait for its answer.
+pub fn request(path: impl AsRef<Path>, request: &Request) -> Result<Response, String> {
+    let path = path.as_ref();
+    let mut stream = UnixStream::connect(path)
+        .map_err(|err| format!("unable to connect to {}: {err}", path.display()))?;
+
+    let mut encoded =
+        serde_json::to_string(request).map_err(|err| format!("unable to encode request: {err}"))?;
+    encoded.push('\n');
+    stream
+        .write_all(encoded.as_bytes())
+        .map_err(|err| format!("unable to send request: {err}"))?;
+
+    let mut line = String::new();
+    BufReader::new(stream)
+        .read_line(&mut line)
+        .map_err(|err| format!("unable to read response: {err}"))?;
+    serde_json::from_str(&line).map_err(|err| format!("invalid response: {err}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn socket_round_trip() {
+        let path = std::env::temp_dir().join(format!("ipc-test-{}.sock", std::process::id()));
+        let (commands, queue) = mpsc::channel::<Command>();
+        let server = Server::start(&path, commands).unwrap();
+        thread::spawn(move || {
+            for command in queue {
+                command.answer(|request| match request {
+                    Request::Status => Response::status(Status {
+                        brightness: Some(40),
+                        ..Status::default()
+                    }),
+                    Request::Show { panel, .. } => Response::error(format!("no panel {panel}")),
+                    _ => Response::ok(),
+                });
+            }
+        });
+
+        let response = request(&path, &Request::Status).unwrap();
+        assert!(response.ok);
+        assert_eq!(response.status.unwrap().brightness, Some(40));
+        assert!(Server::start(&path, mpsc::channel().0).is_err());
+
+        // one request per line, answered in order on the same connection
+        let mut stream = UnixStream::connect(&path).unwrap();
+        stream
+            .write_all(
+                b"{\"cmd\":\"sleep\"}\n\n{\"cmd\":\"show\",\"panel\":2}\n{\"cmd\":\"dance\"}\n",
+            )
+            .unwrap();
+        let mut lines = BufReader::new(stream).lines();
+        let mut next = || -> serde_json::Value {
+            serde_json::from_str(&lines.next().unwrap().unwrap()).unwrap()
+        };
+        assert_eq!(next(), serde_json::json!({"ok": true}));
+        assert_eq!(
+            next(),
+            serde_json::json!({"ok": false, "error": "no panel 2"})
+        );
+        let invalid = next();
+        assert_eq!(invalid["ok"], false);
+        assert!(invalid["error"]
+            .as_str()
+            .unwrap()
+            .starts_with("invalid request"));
+
+        drop(server);
+        assert!(!path.exists());
+        assert!(request(&path, &Request::Status).is_err());
+    }
+
+    #[test]
+    fn late_requests_are_dropped() {
+        let (commands, queue) = mpsc::channel();
+        let response = dispatch_timeout(Request::Reset, &commands, Duration::from_millis(10));
+        assert!(!response.ok);
+
+        let command: Command = queue.recv().unwrap();
+        assert!(!command.answer(|_| panic!("a cancelled request must not run")));
+    }
+
+    #[test]
+    fn started_requests_are_waited_for() {
+        let (commands, queue) = mpsc::channel::<Command>();
+        let handle = thread::spawn(move || {
+            let command = queue.recv().unwrap();
+            command.answer(|request| {
+                thread::sleep(Duration::from_millis(50));
+                assert!(matches!(request, Request::Reset));
+                Response::ok()
+            })
+        });
+        let response = dispatch_timeout(Request::Reset, &commands, Duration::from_millis(10));
+        assert!(response.ok);
+        assert!(handle.join().unwrap());
+    }
+}
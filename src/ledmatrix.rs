@@ -1,5 +1,5 @@
 #![allow(dead_code)]
-use crate::matrix;
+use crate::{gamma::GammaTable, matrix};
 use serialport::{SerialPortInfo, SerialPortType};
 use std::{
     io::{Read, Write},
@@ -24,6 +24,8 @@ const CMD_START: [u8; 2] = [0x32, 0xAC];
 pub struct LedMatrix {
     port: Box<dyn serialport::SerialPort>,
     pub port_info: SerialPortInfo,
+    pub fw_version: String,
+    gamma: GammaTable,
 }
 
 impl LedMatrix {
@@ -55,8 +57,8 @@ impl LedMatrix {
         }
 
         for i in mats.iter_mut() {
-            let fw_version = i.get_fw_version()?;
-            log::info!("{} - {}", i.port_info.port_name, fw_version);
+            i.fw_version = i.get_fw_version()?;
+            log::info!("{} - {}", i.port_info.port_name, i.fw_version);
         }
 
         Ok(mats)
@@ -72,9 +74,18 @@ impl LedMatrix {
         Ok(Self {
             port: port0,
             port_info: portinfo,
+            fw_version: String::new(),
+            gamma: GammaTable::identity(),
         })
     }
 
+    ///
+    /// Set the curve that draw_matrix applies to every LED before sending it.
+    ///
+    pub fn set_gamma(&mut self, gamma: GammaTable) {
+        self.gamma = gamma;
+    }
+
     /// Send one command packet to the LED matrix module.
     pub fn sendcommand(&mut self, cmd: u8, params: Option<&[u8]>) -> Result<(), String> {
         let mut buffer: Vec<u8> = vec![];
@@ -96,6 +107,13 @@ impl LedMatrix {
         Ok(())
     }
 
+    /// Block until every queued byte has been written to the module.
+    pub fn flush(&mut self) -> Result<(), String> {
+        self.port
+            .flush()
+            .map_err(|err| format!("port flush failed: {err}"))
+    }
+
     /// Read up to `numbytes` from serial, waiting up to `timeout` for data.
     pub fn serialread(&mut self, numbytes: usize, timeout: Duration) -> Result<Vec<u8>, String> {
         let start_t = SystemTime::now();
@@ -223,12 +241,14 @@ impl LedMatrix {
     /// Display an entire matrix with individual LED brightness values. Slow updating,
     /// but allows for more complex UIs
     ///
+    /// Values are perceptual levels and go through the gamma table first.
+    ///
     pub fn draw_matrix(
         &mut self,
         mat: [[u8; matrix::MATRIX_WIDTH]; matrix::MATRIX_HEIGHT],
     ) -> Result<(), String> {
         // Transpose array
-        let tpose = matrix::transpose(mat);
+        let tpose = matrix::transpose(self.gamma.apply(mat));
 
         for (col, column_data) in tpose.iter().enumerate() {
             self.set_col(col as u8, *column_data)?;
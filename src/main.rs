@@ -1,15 +1,42 @@
-use std::{process::exit, thread, time::Duration};
+use std::{
+    path::PathBuf,
+    process::exit,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
 
-use clap::Parser;
-use config::{Config, WidgetConfig};
+use clap::{Parser, Subcommand};
+use config::Config;
+use ctl::{CtlArgs, NotifyArgs};
+use daemon::Daemon;
+use gamma::GammaTable;
+use ipc::{Command, Request, Response};
 use ledmatrix::LedMatrix;
-use matrix::{MATRIX_HEIGHT, MATRIX_WIDTH};
-
-use crate::widget::{BatteryWidget, ClockWidget, CpuWidget, MemoryWidget, NetworkWidget, Widget};
+use preview::PreviewArgs;
+use signal_hook::consts::{SIGINT, SIGTERM};
 
+mod ambient;
 mod config;
+mod ctl;
+mod daemon;
+mod dbus;
+mod desktop_notify;
+mod gamma;
+mod idle;
+mod ipc;
 mod ledmatrix;
 mod matrix;
+mod metrics;
+mod notify;
+mod power;
+mod preview;
+mod schedule;
+#[cfg(test)]
+mod test_bus;
 mod widget;
 
 #[derive(Parser)]
@@ -21,54 +48,42 @@ struct Cli {
     #[arg(long)]
     list_widgets: bool,
 
+    /// Show a brightness ramp on every panel to tune the gamma curve
+    #[arg(long)]
+    calibrate: bool,
+
     #[arg(long)]
     config: Option<String>,
+
+    #[command(subcommand)]
+    command: Option<CliCommand>,
+}
+
+#[derive(Subcommand)]
+enum CliCommand {
+    /// Control the running daemon
+    #[command(alias = "ledmatrix-ctl")]
+    Ctl(CtlArgs),
+    /// Flash a message on a panel of the running daemon
+    Notify(NotifyArgs),
+    /// Render the configured widgets to the terminal or image files
+    Preview(PreviewArgs),
 }
 
 enum Program {
     ListMod,
     ListWid,
+    Calibrate,
     Default,
 }
 
-fn validate_widget_placements(
-    widgets: &[(WidgetConfig, Box<dyn Widget>)],
-    panel_count: usize,
-) -> Result<(), String> {
-    for (cfg, widget) in widgets {
-        if cfg.panel >= panel_count {
-            return Err(format!(
-                "widget targets panel {} but only {} panel(s) were detected",
-                cfg.panel, panel_count
-            ));
-        }
-
-        let shape = widget.get_shape();
-        let x_end = cfg
-            .x
-            .checked_add(shape.x)
-            .ok_or_else(|| "widget x position overflowed usize".to_string())?;
-        let y_end = cfg
-            .y
-            .checked_add(shape.y)
-            .ok_or_else(|| "widget y position overflowed usize".to_string())?;
-
-        if x_end > MATRIX_WIDTH || y_end > MATRIX_HEIGHT {
-            return Err(format!(
-                "widget at panel {} with origin ({}, {}) and shape {}x{} exceeds panel bounds {}x{}",
-                cfg.panel, cfg.x, cfg.y, shape.x, shape.y, MATRIX_WIDTH, MATRIX_HEIGHT
-            ));
-        }
-    }
-
-    Ok(())
-}
-
 fn parse_program(cli: &Cli) -> Program {
     if cli.list_modules {
         Program::ListMod
     } else if cli.list_widgets {
         Program::ListWid
+    } else if cli.calibrate {
+        Program::Calibrate
     } else {
         Program::Default
     }
@@ -85,6 +100,13 @@ fn main() {
     let cli = Cli::parse();
     let program = parse_program(&cli);
 
+    let preview = match cli.command {
+        Some(CliCommand::Ctl(args)) => finish(ctl::run(args)),
+        Some(CliCommand::Notify(args)) => finish(ctl::notify(args)),
+        Some(CliCommand::Preview(args)) => Some(args),
+        None => None,
+    };
+
     let config_path = cli.config.unwrap_or_else(|| "./config.toml".to_string());
     let config = match config::load(&config_path) {
         Ok(config) => config,
@@ -94,13 +116,58 @@ fn main() {
         }
     };
 
+    if let Some(args) = preview {
+        finish(preview::run(args, &config));
+    }
+
     match program {
-        Program::Default => loop {
-            if let Err(err) = run(&config) {
-                log::warn!("widget runner exited early: {err}");
+        Program::Default => {
+            let shutdown = Arc::new(AtomicBool::new(false));
+            for signal in [SIGTERM, SIGINT] {
+                // a second signal while shutting down exits immediately
+                let registered = signal_hook::flag::register_conditional_shutdown(
+                    signal,
+                    1,
+                    Arc::clone(&shutdown),
+                )
+                .and_then(|_| signal_hook::flag::register(signal, Arc::clone(&shutdown)));
+                if let Err(err) = registered {
+                    log::error!("unable to register signal handler: {err}");
+                    exit(1);
+                }
             }
-            thread::sleep(Duration::from_millis(1000));
-        },
+
+            let (command_tx, commands) = mpsc::channel();
+            let socket_path = config
+                .general
+                .control_socket
+                .as_ref()
+                .map(PathBuf::from)
+                .unwrap_or_else(ipc::default_socket_path);
+            if let Some(desktop) = &config.general.desktop_notifications {
+                if let Err(err) = desktop_notify::spawn(desktop, command_tx.clone()) {
+                    log::warn!("desktop notification mirroring disabled: {err}");
+                }
+            }
+            let _dbus = config.general.dbus.as_ref().and_then(|dbus| {
+                dbus::spawn(dbus, command_tx.clone())
+                    .inspect_err(|err| log::warn!("D-Bus service disabled: {err}"))
+                    .ok()
+            });
+            let _server = ipc::Server::start(&socket_path, command_tx)
+                .inspect_err(|err| log::warn!("control socket disabled: {err}"))
+                .ok();
+
+            let mut config = config;
+            while !shutdown.load(Ordering::SeqCst) {
+                if let Err(err) = run(&mut config, &config_path, &shutdown, &commands) {
+                    log::warn!("widget runner exited early: {err}");
+                }
+                wait(Duration::from_millis(1000), &shutdown, &commands, |_| {
+                    Response::error("no led modules connected")
+                });
+            }
+        }
         Program::ListMod => {
             if let Err(err) = LedMatrix::detect() {
                 log::error!("unable to detect led matrix modules: {err}");
@@ -120,53 +187,95 @@ fn main() {
                 "Clock Widget:\n \
                 A 9x11 widget that displays the system time in 24hr format.\n"
             );
+        }
+        Program::Calibrate => {
+            if let Err(err) = calibrate(&config) {
+                log::error!("unable to draw calibration pattern: {err}");
+                exit(1);
+            }
         } // _ => {}
     }
 
     exit(0);
 }
 
-fn run(config: &Config) -> Result<(), String> {
+/// Exit after a subcommand, logging its error if it failed.
+fn finish(result: Result<(), String>) -> ! {
+    if let Err(err) = result {
+        log::error!("{err}");
+        exit(1);
+    }
+    exit(0);
+}
+
+fn calibrate(config: &Config) -> Result<(), String> {
+    let gamma = GammaTable::from_config(&config.general.gamma)?;
     let mut mats = LedMatrix::detect()?;
     if mats.is_empty() {
         log::warn!("no led modules found");
         return Ok(());
     }
 
-    // load all widgets
-    let mut widgets: Vec<(WidgetConfig, Box<dyn Widget>)> = Vec::new();
-    for widget in config.widgets.iter() {
-        match &widget.setup {
-            config::WidgetSetup::Cpu(cfg) => {
-                widgets.push((widget.clone(), Box::new(CpuWidget::new(cfg.merge_threads))));
-            }
-            config::WidgetSetup::Memory(_) => {
-                widgets.push((widget.clone(), Box::new(MemoryWidget::new())));
-            }
-            config::WidgetSetup::Network(cfg) => {
-                widgets.push((widget.clone(), Box::new(NetworkWidget::new(&cfg.devices))));
-            }
-            config::WidgetSetup::Battery => {
-                widgets.push((widget.clone(), Box::new(BatteryWidget::new())));
-            }
-            config::WidgetSetup::Clock => {
-                widgets.push((widget.clone(), Box::new(ClockWidget::new())));
-            }
-        }
+    for mat in mats.iter_mut() {
+        mat.set_gamma(gamma.clone());
+        mat.draw_matrix(gamma::calibration_pattern())?;
     }
 
-    validate_widget_placements(&widgets, mats.len())?;
+    Ok(())
+}
 
-    loop {
-        for (idx, mat) in mats.iter_mut().enumerate() {
-            let mut dots = [[0; MATRIX_WIDTH]; MATRIX_HEIGHT];
-            for (config, widget) in widgets.iter_mut().filter(|(c, _)| c.panel == idx) {
-                widget.update();
-                dots = matrix::emplace(dots, widget.as_ref(), config.x, config.y);
+/// Sleep for `duration` while answering control requests with `handle`.
+///
+/// Returns early once `shutdown` is set or after a request that changes what
+/// is shown, so the next frame reflects it right away.
+fn wait(
+    duration: Duration,
+    shutdown: &AtomicBool,
+    commands: &Receiver<Command>,
+    mut handle: impl FnMut(Request) -> Response,
+) {
+    let start = Instant::now();
+    while !shutdown.load(Ordering::SeqCst) {
+        let Some(left) = duration.checked_sub(start.elapsed()) else {
+            break;
+        };
+        if let Ok(command) = commands.recv_timeout(left.min(Duration::from_millis(50))) {
+            let redraw = !matches!(command.request, Request::Status);
+            // a request the client gave up on changes nothing
+            if command.answer(&mut handle) && redraw {
+                break;
             }
-            mat.draw_matrix(dots)?;
         }
+    }
+}
+
+fn run(
+    config: &mut Config,
+    config_path: &str,
+    shutdown: &AtomicBool,
+    commands: &Receiver<Command>,
+) -> Result<(), String> {
+    let mats = LedMatrix::detect()?;
+    if mats.is_empty() {
+        log::warn!("no led modules found");
+        return Ok(());
+    }
 
-        thread::sleep(Duration::from_millis(500));
+    let mut daemon = Daemon::new(config.clone(), config_path, mats)?;
+    let mut result = Ok(());
+    while !shutdown.load(Ordering::SeqCst) {
+        // the exit action still runs when a tick fails
+        result = daemon.tick();
+        if result.is_err() {
+            break;
+        }
+        wait(daemon.update_interval(), shutdown, commands, |request| {
+            daemon.handle(request)
+        });
     }
+    // reconnected panels start with the last reloaded config
+    *config = daemon.config().clone();
+
+    let shutdown = daemon.shutdown();
+    result.and(shutdown)
 }
@@ -1,4 +1,4 @@
-use crate::widget::Widget;
+use crate::widget::{Widget, OFF, ON_DIM, ON_FULL};
 
 pub(crate) const MATRIX_WIDTH: usize = 9;
 pub(crate) const MATRIX_HEIGHT: usize = 34;
@@ -80,3 +80,69 @@ pub fn emplace(orig: Matrix, widget: &dyn Widget, x: usize, y: usize) -> Matrix
 
     out
 }
+
+/// Parse a frame drawn as text, one string per row from the top.
+///
+/// `#` is a fully lit LED, `+` a dimmed one and `.` or a space is off.
+/// Missing rows and columns stay off.
+pub fn from_text_art(rows: &[String]) -> Result<Matrix, String> {
+    if rows.len() > MATRIX_HEIGHT {
+        return Err(format!(
+            "{} rows exceed the {MATRIX_HEIGHT} row panel",
+            rows.len()
+        ));
+    }
+
+    let mut out: Matrix = [[OFF; MATRIX_WIDTH]; MATRIX_HEIGHT];
+    for (y, row) in rows.iter().enumerate() {
+        if row.chars().count() > MATRIX_WIDTH {
+            return Err(format!(
+                "row {y} is wider than the {MATRIX_WIDTH} column panel"
+            ));
+        }
+        for (x, cell) in row.chars().enumerate() {
+            out[y][x] = match cell {
+                '#' => ON_FULL,
+                '+' => ON_DIM,
+                '.' | ' ' => OFF,
+                other => return Err(format!("unknown pixel '{other}' in row {y}")),
+            };
+        }
+    }
+
+    Ok(out)
+}
+
+/// Draw a frame as text art, the inverse of `from_text_art`.
+///
+/// Levels at or above `ON_FULL` become `#`, dimmer lit LEDs `+`.
+pub fn to_text_art(mat: &Matrix) -> Vec<String> {
+    mat.iter()
+        .map(|row| {
+            row.iter()
+                .map(|&level| match level {
+                    OFF => '.',
+                    level if level >= ON_FULL => '#',
+                    _ => '+',
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Build a frame from row-major brightness values, one per LED.
+pub fn from_pixels(pixels: &[u8]) -> Result<Matrix, String> {
+    if pixels.len() != MATRIX_WIDTH * MATRIX_HEIGHT {
+        return Err(format!(
+            "expected {} pixel values, got {}",
+            MATRIX_WIDTH * MATRIX_HEIGHT,
+            pixels.len()
+        ));
+    }
+
+    let mut out: Matrix = [[OFF; MATRIX_WIDTH]; MATRIX_HEIGHT];
+    for (row, values) in out.iter_mut().zip(pixels.chunks(MATRIX_WIDTH)) {
+        row.copy_from_slice(values);
+    }
+    Ok(out)
+}
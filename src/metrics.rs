@@ -0,0 +1,683 @@
+//! System metrics sampled once per widget tick and shared by every widget.
+//!
+//! Widgets read the latest sample through the `widget::source` traits, which
+//! `MetricsHandle` implements, so two widgets showing the same data cost one
+//! refresh. A source is only sampled once some widget has read it.
+
+use std::{
+    cell::RefCell,
+    collections::{HashMap, VecDeque},
+    fmt,
+    rc::Rc,
+    str::FromStr,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::widget::{
+    sensor_matches,
+    source::{
+        BatterySource, BatteryStatus, CpuFrequency, CpuLocation, CpuSource, MemorySource,
+        MemoryStats, NetworkSample, NetworkSource, SystemBattery, SystemCpu, SystemMemory,
+        SystemNetworks, SystemTemperatures, TemperatureReading, TemperatureSource,
+    },
+};
+
+/// A value whose history is recorded on every sample.
+///
+/// Written in the config as `cpu`, `core3`, `memory`, `swap`, `rx:wlan0`,
+/// `tx:wlan0`, `battery`, `power` or `temp:Tctl`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Metric {
+    /// Average usage of all CPUs in percent.
+    CpuTotal,
+    /// Usage of one logical CPU in percent.
+    Core(usize),
+    /// Used memory in percent of the total.
+    Memory,
+    /// Used swap in percent of the total.
+    Swap,
+    /// Bytes per second received by an interface.
+    Received(String),
+    /// Bytes per second sent by an interface.
+    Transmitted(String),
+    /// Battery charge in percent.
+    Battery,
+    /// Battery charge or discharge rate in watts.
+    BatteryPower,
+    /// Hottest hwmon sensor matching a pattern like the temperature widget's,
+    /// in °C.
+    Temperature(String),
+}
+
+impl Metric {
+    /// Whether the metric is a percentage, as opposed to an open-ended value.
+    pub fn is_percent(&self) -> bool {
+        matches!(
+            self,
+            Metric::CpuTotal | Metric::Core(_) | Metric::Memory | Metric::Swap | Metric::Battery
+        )
+    }
+}
+
+impl FromStr for Metric {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        if let Some(interface) = value.strip_prefix("rx:") {
+            return Ok(Metric::Received(interface.to_string()));
+        }
+        if let Some(interface) = value.strip_prefix("tx:") {
+            return Ok(Metric::Transmitted(interface.to_string()));
+        }
+        if let Some(sensor) = value.strip_prefix("temp:") {
+            return Ok(Metric::Temperature(sensor.to_string()));
+        }
+        if let Some(index) = value.strip_prefix("core") {
+            return index
+                .parse()
+                .map(Metric::Core)
+                .map_err(|_| format!("invalid core number in metric \"{value}\""));
+        }
+        match value {
+            "cpu" => Ok(Metric::CpuTotal),
+            "memory" => Ok(Metric::Memory),
+            "swap" => Ok(Metric::Swap),
+            "battery" => Ok(Metric::Battery),
+            "power" => Ok(Metric::BatteryPower),
+            other => Err(format!("unknown metric \"{other}\"")),
+        }
+    }
+}
+
+impl TryFrom<String> for Metric {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl fmt::Display for Metric {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Metric::CpuTotal => write!(f, "cpu"),
+            Metric::Core(index) => write!(f, "core{index}"),
+            Metric::Memory => write!(f, "memory"),
+            Metric::Swap => write!(f, "swap"),
+            Metric::Received(interface) => write!(f, "rx:{interface}"),
+            Metric::Transmitted(interface) => write!(f, "tx:{interface}"),
+            Metric::Battery => write!(f, "battery"),
+            Metric::BatteryPower => write!(f, "power"),
+            Metric::Temperature(sensor) => write!(f, "temp:{sensor}"),
+        }
+    }
+}
+
+impl From<Metric> for String {
+    fn from(metric: Metric) -> Self {
+        metric.to_string()
+    }
+}
+
+/// Fixed-size ring buffer of the most recent samples of a metric.
+#[derive(Debug, Clone)]
+pub struct History {
+    samples: VecDeque<f32>,
+    capacity: usize,
+}
+
+impl History {
+    fn new(capacity: usize) -> Self {
+        Self {
+            samples: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    fn push(&mut self, value: f32) {
+        while self.samples.len() >= self.capacity.max(1) {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(value);
+    }
+
+    /// Samples from the oldest to the newest.
+    pub fn values(&self) -> impl Iterator<Item = f32> + '_ {
+        self.samples.iter().copied()
+    }
+}
+
+/// Sources and their latest samples, `None` until a widget asked for one.
+pub struct Metrics {
+    cpu_source: Box<dyn CpuSource>,
+    memory_source: Box<dyn MemorySource>,
+    network_source: Box<dyn NetworkSource>,
+    battery_source: Box<dyn BatterySource>,
+    temperature_source: Box<dyn TemperatureSource>,
+    cpu: Option<Vec<f32>>,
+    memory: Option<MemoryStats>,
+    network: Option<NetworkSample>,
+    battery: Option<Option<BatteryStatus>>,
+    temperatures: Option<Vec<TemperatureReading>>,
+    /// Sensor patterns of the temperature metrics read so far.
+    temperature_sensors: Vec<String>,
+    history: HashMap<Metric, History>,
+    history_length: usize,
+}
+
+impl Metrics {
+    /// Metrics of the running system keeping `history_length` samples, with
+    /// temperatures read from the hwmon chips under `hwmon`.
+    pub fn new(history_length: usize, hwmon: &str) -> Self {
+        Self::with_sources(
+            history_length,
+            Box::new(SystemCpu::new()),
+            Box::new(SystemMemory::new()),
+            Box::new(SystemNetworks::new()),
+            Box::new(SystemBattery),
+            Box::new(SystemTemperatures::new(hwmon)),
+        )
+    }
+
+    pub fn with_sources(
+        history_length: usize,
+        cpu_source: Box<dyn CpuSource>,
+        memory_source: Box<dyn MemorySource>,
+        network_source: Box<dyn NetworkSource>,
+        battery_source: Box<dyn BatterySource>,
+        temperature_source: Box<dyn TemperatureSource>,
+    ) -> Self {
+        Self {
+            cpu_source,
+            memory_source,
+            network_source,
+            battery_source,
+            temperature_source,
+            cpu: None,
+            memory: None,
+            network: None,
+            battery: None,
+            temperatures: None,
+            temperature_sensors: Vec::new(),
+            history: HashMap::new(),
+            history_length,
+        }
+    }
+
+    fn record(&mut self, metric: Metric, value: f32) {
+        let length = self.history_length;
+        self.history
+            .entry(metric)
+            .or_insert_with(|| History::new(length))
+            .push(value);
+    }
+
+    fn sample_cpu(&mut self) {
+        let usages = self.cpu_source.usages();
+        if !usages.is_empty() {
+            let total = usages.iter().sum::<f32>() / usages.len() as f32;
+            self.record(Metric::CpuTotal, total);
+        }
+        for (idx, &usage) in usages.iter().enumerate() {
+            self.record(Metric::Core(idx), usage);
+        }
+        self.cpu = Some(usages);
+    }
+
+    fn sample_memory(&mut self) {
+        let memory = self.memory_source.memory();
+        let percent = |used: u64, total: u64| {
+            if total == 0 {
+                0.0
+            } else {
+                used as f32 * 100.0 / total as f32
+            }
+        };
+        self.record(Metric::Memory, percent(memory.used, memory.total));
+        self.record(Metric::Swap, percent(memory.used_swap, memory.total_swap));
+        self.memory = Some(memory);
+    }
+
+    fn sample_network(&mut self) {
+        let sample = self.network_source.sample();
+        let secs = sample.elapsed.as_secs_f32().max(0.001);
+        for interface in sample.interfaces.iter() {
+            self.record(
+                Metric::Received(interface.name.clone()),
+                interface.received as f32 / secs,
+            );
+            self.record(
+                Metric::Transmitted(interface.name.clone()),
+                interface.transmitted as f32 / secs,
+            );
+        }
+        self.network = Some(sample);
+    }
+
+    fn sample_battery(&mut self) {
+        let status = self.battery_source.status();
+        if let Some(status) = status {
+            self.record(Metric::Battery, status.percent);
+            self.record(Metric::BatteryPower, status.power);
+        }
+        self.battery = Some(status);
+    }
+
+    /// Record the hottest of the latest readings matching `pattern`, nothing
+    /// without a match.
+    fn record_temperature(&mut self, pattern: &str) {
+        let hottest = self
+            .temperatures
+            .iter()
+            .flatten()
+            .filter(|reading| sensor_matches(pattern, &reading.chip, &reading.label))
+            .map(|reading| reading.celsius)
+            .reduce(f32::max);
+        if let Some(celsius) = hottest {
+            self.record(Metric::Temperature(pattern.to_string()), celsius);
+        }
+    }
+
+    fn sample_temperatures(&mut self) {
+        self.temperatures = Some(self.temperature_source.temperatures());
+        for pattern in self.temperature_sensors.clone() {
+            self.record_temperature(&pattern);
+        }
+    }
+
+    /// Refresh every source a widget has read so far.
+    pub fn sample(&mut self) {
+        if self.cpu.is_some() {
+            self.sample_cpu();
+        }
+        if self.memory.is_some() {
+            self.sample_memory();
+        }
+        if self.network.is_some() {
+            self.sample_network();
+        }
+        if self.battery.is_some() {
+            self.sample_battery();
+        }
+        if self.temperatures.is_some() {
+            self.sample_temperatures();
+        }
+    }
+
+    /// Stop sampling every source until a widget reads it again, for when
+    /// the widgets are replaced. Recorded history is kept.
+    pub fn unwatch(&mut self) {
+        self.cpu = None;
+        self.memory = None;
+        self.network = None;
+        self.battery = None;
+        self.temperatures = None;
+        self.temperature_sensors.clear();
+    }
+
+    /// Start sampling the source behind `metric` if nobody read it yet.
+    fn watch(&mut self, metric: &Metric) {
+        match metric {
+            Metric::CpuTotal | Metric::Core(_) if self.cpu.is_none() => self.sample_cpu(),
+            Metric::Memory | Metric::Swap if self.memory.is_none() => self.sample_memory(),
+            Metric::Received(_) | Metric::Transmitted(_) if self.network.is_none() => {
+                self.sample_network()
+            }
+            Metric::Battery | Metric::BatteryPower if self.battery.is_none() => {
+                self.sample_battery()
+            }
+            Metric::Temperature(sensor) if !self.temperature_sensors.contains(sensor) => {
+                self.temperature_sensors.push(sensor.clone());
+                if self.temperatures.is_none() {
+                    self.sample_temperatures();
+                } else {
+                    self.record_temperature(sensor);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Recorded samples of `metric`, empty if it was never sampled.
+    pub fn history(&self, metric: &Metric) -> Vec<f32> {
+        self.history
+            .get(metric)
+            .map(|history| history.values().collect())
+            .unwrap_or_default()
+    }
+
+    /// Change how many samples are kept, dropping the oldest ones.
+    pub fn set_history_length(&mut self, history_length: usize) {
+        self.history_length = history_length;
+        for history in self.history.values_mut() {
+            history.capacity = history_length;
+            while history.samples.len() > history_length.max(1) {
+                history.samples.pop_front();
+            }
+        }
+    }
+}
+
+/// Shared access to the metrics of one widget loop.
+#[derive(Clone)]
+pub struct MetricsHandle(Rc<RefCell<Metrics>>);
+
+impl MetricsHandle {
+    pub fn new(metrics: Metrics) -> Self {
+        Self(Rc::new(RefCell::new(metrics)))
+    }
+
+    pub fn sample(&self) {
+        self.0.borrow_mut().sample();
+    }
+
+    /// Recorded samples of `metric`, sampling its source from now on.
+    pub fn history(&self, metric: &Metric) -> Vec<f32> {
+        let mut metrics = self.0.borrow_mut();
+        metrics.watch(metric);
+        metrics.history(metric)
+    }
+
+    pub fn set_history_length(&self, history_length: usize) {
+        self.0.borrow_mut().set_history_length(history_length);
+    }
+
+    pub fn unwatch(&self) {
+        self.0.borrow_mut().unwatch();
+    }
+}
+
+// The first read of a source samples it right away, after that the widgets
+// see the sample taken at the start of the tick.
+
+impl CpuSource for MetricsHandle {
+    fn usages(&mut self) -> Vec<f32> {
+        let mut metrics = self.0.borrow_mut();
+        if metrics.cpu.is_none() {
+            metrics.sample_cpu();
+        }
+        metrics.cpu.clone().unwrap_or_default()
+    }
+
+    fn topology(&mut self) -> Vec<CpuLocation> {
+        self.0.borrow_mut().cpu_source.topology()
+    }
+
+    fn frequencies(&mut self) -> Vec<CpuFrequency> {
+        self.0.borrow_mut().cpu_source.frequencies()
+    }
+}
+
+impl MemorySource for MetricsHandle {
+    fn memory(&mut self) -> MemoryStats {
+        let mut metrics = self.0.borrow_mut();
+        if metrics.memory.is_none() {
+            metrics.sample_memory();
+        }
+        metrics.memory.unwrap_or_default()
+    }
+}
+
+impl NetworkSource for MetricsHandle {
+    fn sample(&mut self) -> NetworkSample {
+        let mut metrics = self.0.borrow_mut();
+        if metrics.network.is_none() {
+            metrics.sample_network();
+        }
+        metrics.network.clone().unwrap_or_default()
+    }
+}
+
+impl BatterySource for MetricsHandle {
+    fn status(&mut self) -> Option<BatteryStatus> {
+        let mut metrics = self.0.borrow_mut();
+        if metrics.battery.is_none() {
+            metrics.sample_battery();
+        }
+        metrics.battery.flatten()
+    }
+}
+
+impl TemperatureSource for MetricsHandle {
+    fn temperatures(&mut self) -> Vec<TemperatureReading> {
+        let mut metrics = self.0.borrow_mut();
+        if metrics.temperatures.is_none() {
+            metrics.sample_temperatures();
+        }
+        metrics.temperatures.clone().unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, rc::Rc};
+
+    use super::*;
+    use crate::widget::{
+        snapshot::{FixedBattery, FixedMemory, FixedNetwork, FixedTemperatures},
+        source::ChargeState,
+    };
+
+    /// Counts refreshes and reports the refresh number as every usage.
+    struct CountingCpu(Rc<Cell<usize>>);
+
+    impl CpuSource for CountingCpu {
+        fn usages(&mut self) -> Vec<f32> {
+            self.0.set(self.0.get() + 1);
+            vec![self.0.get() as f32; 2]
+        }
+
+        fn topology(&mut self) -> Vec<CpuLocation> {
+            (0..2)
+                .map(|core| CpuLocation {
+                    package: 0,
+                    core,
+                    cache: 0,
+                })
+                .collect()
+        }
+    }
+
+    fn metrics(history_length: usize) -> (MetricsHandle, Rc<Cell<usize>>) {
+        let refreshes = Rc::new(Cell::new(0));
+        let metrics = Metrics::with_sources(
+            history_length,
+            Box::new(CountingCpu(Rc::clone(&refreshes))),
+            Box::new(FixedMemory(MemoryStats::default())),
+            Box::new(FixedNetwork(vec![("wlan0", 2000, 1000)])),
+            Box::new(FixedBattery(Some(BatteryStatus {
+                percent: 80.0,
+                state: ChargeState::Discharging,
+                power: 12.5,
+                ..Default::default()
+            }))),
+            Box::new(FixedTemperatures(vec![
+                ("k10temp", "Tctl", 61.0),
+                ("nvme", "Composite", 38.0),
+                ("nvme", "Sensor 1", 44.5),
+            ])),
+        );
+        (MetricsHandle::new(metrics), refreshes)
+    }
+
+    #[test]
+    fn widgets_share_one_sample() {
+        let (metrics, refreshes) = metrics(8);
+        let (mut first, mut second) = (metrics.clone(), metrics.clone());
+        assert_eq!(first.usages(), vec![1.0, 1.0]);
+
+        metrics.sample();
+        assert_eq!(first.usages(), vec![2.0, 2.0]);
+        assert_eq!(second.usages(), vec![2.0, 2.0]);
+        assert_eq!(refreshes.get(), 2);
+    }
+
+    #[test]
+    fn unread_sources_are_not_sampled() {
+        let (metrics, refreshes) = metrics(8);
+        metrics.sample();
+        assert_eq!(refreshes.get(), 0);
+        assert_eq!(metrics.history(&Metric::Memory).len(), 1);
+        assert_eq!(refreshes.get(), 0);
+    }
+
+    #[test]
+    fn unwatched_sources_stop_sampling() {
+        let (metrics, refreshes) = metrics(8);
+        metrics.clone().usages();
+        metrics.sample();
+        assert_eq!(refreshes.get(), 2);
+
+        metrics.unwatch();
+        metrics.sample();
+        assert_eq!(refreshes.get(), 2);
+        // the history stays, reading it through the handle would watch again
+        assert_eq!(
+            metrics.0.borrow().history(&Metric::CpuTotal),
+            vec![1.0, 2.0]
+        );
+
+        // reading again resumes sampling
+        metrics.clone().usages();
+        metrics.sample();
+        assert_eq!(refreshes.get(), 4);
+    }
+
+    #[test]
+    fn metric_names_round_trip() {
+        for name in [
+            "cpu",
+            "core12",
+            "memory",
+            "swap",
+            "rx:wlan0",
+            "tx:eth0",
+            "battery",
+            "power",
+            "temp:Tctl",
+            "temp:amdgpu:edge",
+        ] {
+            assert_eq!(name.parse::<Metric>().unwrap().to_string(), name);
+        }
+        assert_eq!("power".parse(), Ok(Metric::BatteryPower));
+        assert_eq!(
+            "temp:nvme:*".parse(),
+            Ok(Metric::Temperature("nvme:*".to_string()))
+        );
+        assert!("core".parse::<Metric>().is_err());
+        assert!("gpu".parse::<Metric>().is_err());
+    }
+
+    #[test]
+    fn metric_serde_round_trip() {
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Setup {
+            metric: Metric,
+        }
+
+        for metric in [
+            Metric::Battery,
+            Metric::BatteryPower,
+            Metric::Temperature("k10temp:Tctl".to_string()),
+        ] {
+            let setup = Setup { metric };
+            let toml = toml::to_string(&setup).unwrap();
+            assert_eq!(toml::from_str::<Setup>(&toml).unwrap(), setup);
+        }
+        assert_eq!(
+            toml::from_str::<Setup>("metric = \"temp:Tctl\"").unwrap(),
+            Setup {
+                metric: Metric::Temperature("Tctl".to_string())
+            }
+        );
+        assert!(toml::from_str::<Setup>("metric = \"watts\"").is_err());
+    }
+
+    #[test]
+    fn temperature_widgets_share_the_readings() {
+        let (metrics, _) = metrics(3);
+        let mut widget: Box<dyn TemperatureSource> = Box::new(metrics.clone());
+        assert_eq!(widget.temperatures().len(), 3);
+        assert_eq!(
+            metrics.history(&Metric::Temperature("Tctl".to_string())),
+            vec![61.0]
+        );
+        metrics.sample();
+        assert_eq!(widget.temperatures()[0].celsius, 61.0);
+        assert_eq!(
+            metrics.history(&Metric::Temperature("Tctl".to_string())),
+            vec![61.0, 61.0]
+        );
+    }
+
+    #[test]
+    fn battery_power_and_temperatures() {
+        let (metrics, _) = metrics(3);
+        assert_eq!(metrics.history(&Metric::BatteryPower), vec![12.5]);
+        assert_eq!(
+            metrics.history(&Metric::Temperature("nvme:*".to_string())),
+            vec![44.5]
+        );
+        assert!(metrics
+            .history(&Metric::Temperature("amdgpu:edge".to_string()))
+            .is_empty());
+
+        metrics.sample();
+        assert_eq!(metrics.history(&Metric::Battery), vec![80.0, 80.0]);
+        assert_eq!(
+            metrics.history(&Metric::Temperature("Tctl".to_string())),
+            vec![61.0]
+        );
+        metrics.sample();
+        assert_eq!(
+            metrics.history(&Metric::Temperature("nvme:*".to_string())),
+            vec![44.5, 44.5, 44.5]
+        );
+    }
+
+    #[test]
+    fn history_ring_buffer() {
+        let mut history = History::new(2);
+        for value in [1.0, 2.0, 3.0] {
+            history.push(value);
+        }
+        assert_eq!(history.values().collect::<Vec<_>>(), vec![2.0, 3.0]);
+
+        // a zero length still keeps the newest sample
+        let mut history = History::new(0);
+        history.push(1.0);
+        history.push(2.0);
+        assert_eq!(history.values().collect::<Vec<_>>(), vec![2.0]);
+    }
+
+    #[test]
+    fn history_keeps_the_newest_samples() {
+        let (metrics, _) = metrics(3);
+        let mut cpu = metrics.clone();
+        cpu.usages();
+        for _ in 0..4 {
+            metrics.sample();
+        }
+        assert_eq!(metrics.history(&Metric::CpuTotal), vec![3.0, 4.0, 5.0]);
+        assert_eq!(metrics.history(&Metric::Core(1)), vec![3.0, 4.0, 5.0]);
+
+        metrics.set_history_length(2);
+        assert_eq!(metrics.history(&Metric::CpuTotal), vec![4.0, 5.0]);
+    }
+
+    #[test]
+    fn network_rates_per_interface() {
+        let (metrics, _) = metrics(3);
+        NetworkSource::sample(&mut metrics.clone());
+        assert_eq!(
+            metrics.history(&Metric::Received("wlan0".to_string())),
+            vec![2000.0]
+        );
+        assert_eq!(
+            metrics.history(&Metric::Transmitted("wlan0".to_string())),
+            vec![1000.0]
+        );
+    }
+}
@@ -0,0 +1,421 @@
+use std::time::{Duration, Instant};
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    matrix::{Matrix, MATRIX_HEIGHT, MATRIX_WIDTH},
+    widget::{
+        font::{self, GLYPH_HEIGHT},
+        OFF, ON_DIM, ON_FULL,
+    },
+};
+
+/// Time it takes text to move by one column.
+pub const SCROLL_STEP: Duration = Duration::from_millis(120);
+
+/// Shortest time a notification stays up when no duration was requested.
+const MIN_DURATION: Duration = Duration::from_secs(3);
+
+const ICON_SIZE: usize = 9;
+const ICON_Y: usize = 6;
+const TEXT_Y_WITH_ICON: usize = 19;
+const TEXT_Y: usize = (MATRIX_HEIGHT - GLYPH_HEIGHT) / 2;
+
+/// How urgently a notification wants the panel.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, ValueEnum,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Priority {
+    Low,
+    #[default]
+    Normal,
+    High,
+    /// Interrupts anything and blinks the panel edges.
+    Urgent,
+}
+
+/// Built-in 9x9 pictograms shown above the text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Icon {
+    Info,
+    Warning,
+    Error,
+    Check,
+    Bell,
+    Mail,
+}
+
+impl Icon {
+    fn rows(self) -> [&'static str; ICON_SIZE] {
+        match self {
+            Icon::Info => [
+                "..#####..",
+                ".#.....#.",
+                "#...#...#",
+                "#.......#",
+                "#...#...#",
+                "#...#...#",
+                "#...#...#",
+                ".#.....#.",
+                "..#####..",
+            ],
+            Icon::Warning => [
+                "....#....",
+                "....#....",
+                "...#.#...",
+                "...#.#...",
+                "..#.#.#..",
+                "..#.#.#..",
+                ".#.....#.",
+                ".#..#..#.",
+                "#########",
+            ],
+            Icon::Error => [
+                "#.......#",
+                ".#.....#.",
+                "..#...#..",
+                "...#.#...",
+                "....#....",
+                "...#.#...",
+                "..#...#..",
+                ".#.....#.",
+                "#.......#",
+            ],
+            Icon::Check => [
+                ".........",
+                "........#",
+                ".......##",
+                "......##.",
+                "#....##..",
+                "##..##...",
+                ".####....",
+                "..##.....",
+                ".........",
+            ],
+            Icon::Bell => [
+                "....#....",
+                "...###...",
+                "..#####..",
+                "..#####..",
+                "..#####..",
+                ".#######.",
+                "#########",
+                ".........",
+                "....#....",
+            ],
+            Icon::Mail => [
+                ".........",
+                "#########",
+                "##.....##",
+                "#.#...#.#",
+                "#..#.#..#",
+                "#...#...#",
+                "#.......#",
+                "#########",
+                ".........",
+            ],
+        }
+    }
+}
+
+/// A message that temporarily replaces the widgets of one panel.
+#[derive(Debug, Clone)]
+pub struct Notification {
+    pub panel: usize,
+    pub text: String,
+    pub icon: Option<Icon>,
+    pub priority: Priority,
+    pub duration: Option<Duration>,
+}
+
+impl Notification {
+    /// Requested duration, or long enough to scroll the text through once.
+    fn duration(&self) -> Duration {
+        self.duration.unwrap_or_else(|| {
+            let columns = font::text_columns(&self.text).len() + MATRIX_WIDTH;
+            (SCROLL_STEP * columns as u32 + Duration::from_secs(1)).max(MIN_DURATION)
+        })
+    }
+
+    /// Render the notification as it looks `elapsed` after it appeared.
+    fn render(&self, elapsed: Duration) -> Matrix {
+        let mut cells = vec![OFF; MATRIX_WIDTH * MATRIX_HEIGHT];
+
+        if let Some(icon) = self.icon {
+            for (row, line) in icon.rows().iter().enumerate() {
+                for (col, pixel) in line.chars().enumerate() {
+                    if pixel == '#' {
+                        cells[(ICON_Y + row) * MATRIX_WIDTH + col] = ON_FULL;
+                    }
+                }
+            }
+        }
+
+        let text_y = if self.icon.is_some() {
+            TEXT_Y_WITH_ICON
+        } else {
+            TEXT_Y
+        };
+        let columns = font::text_columns(&self.text);
+        let offset = if columns.len() <= MATRIX_WIDTH {
+            // short text fits, center it instead of scrolling
+            -(((MATRIX_WIDTH - columns.len()) / 2) as isize)
+        } else {
+            let cycle = columns.len() + MATRIX_WIDTH;
+            let step = (elapsed.as_millis() / SCROLL_STEP.as_millis()) as usize % cycle;
+            step as isize - MATRIX_WIDTH as isize
+        };
+        font::draw_columns(
+            &mut cells,
+            MATRIX_WIDTH,
+            0,
+            text_y,
+            MATRIX_WIDTH,
+            &columns,
+            offset,
+            ON_FULL,
+        );
+
+        // urgent messages blink the top and bottom edge
+        if self.priority == Priority::Urgent && (elapsed.as_millis() / 500).is_multiple_of(2) {
+            for col in 0..MATRIX_WIDTH {
+                cells[col] = ON_DIM;
+                cells[(MATRIX_HEIGHT - 1) * MATRIX_WIDTH + col] = ON_DIM;
+            }
+        }
+
+        let mut frame: Matrix = [[OFF; MATRIX_WIDTH]; MATRIX_HEIGHT];
+        for (row, values) in frame.iter_mut().zip(cells.chunks(MATRIX_WIDTH)) {
+            row.copy_from_slice(values);
+        }
+        frame
+    }
+
+    fn scrolls(&self) -> bool {
+        font::text_columns(&self.text).len() > MATRIX_WIDTH || self.priority == Priority::Urgent
+    }
+}
+
+struct Shown {
+    notification: Notification,
+    since: Instant,
+}
+
+/// Per-panel notification state: what is on screen and what is waiting.
+#[derive(Default)]
+pub struct Notifier {
+    shown: Vec<Shown>,
+    queue: Vec<Notification>,
+}
+
+impl Notifier {
+    /// Show `notification` right away if it outranks what its panel shows,
+    /// otherwise queue it behind everything of equal or higher priority.
+    pub fn push(&mut self, notification: Notification, now: Instant) {
+        let current = self
+            .shown
+            .iter()
+            .position(|shown| shown.notification.panel == notification.panel);
+
+        match current {
+            None => self.shown.push(Shown {
+                notification,
+                since: now,
+            }),
+            Some(idx) if notification.priority > self.shown[idx].notification.priority => {
+                // the interrupted message starts over once it's its turn again
+                let interrupted = std::mem::replace(
+                    &mut self.shown[idx],
+                    Shown {
+                        notification,
+                        since: now,
+                    },
+                );
+                self.enqueue(interrupted.notification);
+            }
+            Some(_) => self.enqueue(notification),
+        }
+    }
+
+    fn enqueue(&mut self, notification: Notification) {
+        let pos = self
+            .queue
+            .iter()
+            .position(|queued| queued.priority < notification.priority)
+            .unwrap_or(self.queue.len());
+        self.queue.insert(pos, notification);
+    }
+
+    /// Frame for `panel`, or `None` when its widgets should be drawn.
+    pub fn frame(&mut self, panel: usize, now: Instant) -> Option<Matrix> {
+        if let Some(idx) = self
+            .shown
+            .iter()
+            .position(|shown| shown.notification.panel == panel)
+        {
+            let shown = &self.shown[idx];
+            if now.duration_since(shown.since) < shown.notification.duration() {
+                return Some(shown.notification.render(now.duration_since(shown.since)));
+            }
+            self.shown.remove(idx);
+        }
+
+        let next = self.queue.iter().position(|queued| queued.panel == panel)?;
+        let notification = self.queue.remove(next);
+        let frame = notification.render(Duration::ZERO);
+        self.shown.push(Shown {
+            notification,
+            since: now,
+        });
+        Some(frame)
+    }
+
+    /// Whether a shown notification needs frames faster than the widgets.
+    pub fn is_animating(&self) -> bool {
+        self.shown.iter().any(|shown| shown.notification.scrolls())
+    }
+
+    pub fn clear(&mut self) {
+        self.shown.clear();
+        self.queue.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECOND: Duration = Duration::from_secs(1);
+
+    fn note(panel: usize, text: &str, priority: Priority) -> Notification {
+        Notification {
+            panel,
+            text: text.to_string(),
+            icon: None,
+            priority,
+            duration: Some(SECOND),
+        }
+    }
+
+    fn looks_like(frame: Option<Matrix>, text: &str) -> bool {
+        frame == Some(note(0, text, Priority::Normal).render(Duration::ZERO))
+    }
+
+    #[test]
+    fn higher_priorities_interrupt_and_go_first() {
+        let start = Instant::now();
+        let mut notifier = Notifier::default();
+        assert_eq!(notifier.frame(0, start), None);
+
+        notifier.push(note(0, "A", Priority::Normal), start);
+        notifier.push(note(0, "B", Priority::Low), start);
+        notifier.push(note(0, "C", Priority::High), start);
+        notifier.push(note(0, "D", Priority::Normal), start);
+        notifier.push(note(0, "E", Priority::High), start);
+
+        // C interrupted A, E only ties with C so it waits; the interrupted A
+        // goes back in line ahead of D, which arrived later
+        let mut now = start;
+        for text in ["C", "E", "A", "D", "B"] {
+            assert!(looks_like(notifier.frame(0, now), text), "{text}");
+            now += SECOND;
+        }
+        assert_eq!(notifier.frame(0, now), None);
+    }
+
+    #[test]
+    fn notifications_expire_after_their_duration() {
+        let start = Instant::now();
+        let mut notifier = Notifier::default();
+        notifier.push(note(0, "A", Priority::Normal), start);
+        notifier.push(note(0, "B", Priority::Normal), start);
+
+        assert!(looks_like(notifier.frame(0, start), "A"));
+        assert!(looks_like(notifier.frame(0, start + SECOND / 2), "A"));
+        // the next one starts when the first runs out, not when pushed
+        let later = start + SECOND * 3;
+        assert!(looks_like(notifier.frame(0, later), "B"));
+        assert!(looks_like(notifier.frame(0, later + SECOND / 2), "B"));
+        assert_eq!(notifier.frame(0, later + SECOND), None);
+
+        // without a duration short text stays up for the minimum
+        notifier.push(
+            Notification {
+                duration: None,
+                ..note(0, "C", Priority::Normal)
+            },
+            start,
+        );
+        assert!(looks_like(notifier.frame(0, start + MIN_DURATION / 2), "C"));
+        assert_eq!(notifier.frame(0, start + MIN_DURATION), None);
+    }
+
+    #[test]
+    fn panels_queue_independently() {
+        let now = Instant::now();
+        let mut notifier = Notifier::default();
+        notifier.push(note(0, "A", Priority::Low), now);
+        notifier.push(note(1, "B", Priority::Urgent), now);
+        notifier.push(note(1, "C", Priority::Low), now);
+
+        assert!(looks_like(notifier.frame(0, now), "A"));
+        assert!(notifier.frame(1, now).is_some());
+        assert!(looks_like(notifier.frame(1, now + SECOND), "C"));
+        assert_eq!(notifier.frame(2, now), None);
+
+        notifier.clear();
+        assert_eq!(notifier.frame(1, now), None);
+    }
+
+    #[test]
+    fn only_moving_notifications_redraw() {
+        let start = Instant::now();
+        let mut notifier = Notifier::default();
+        notifier.push(note(0, "OK", Priority::Normal), start);
+        notifier.push(
+            Notification {
+                duration: Some(SECOND * 4),
+                ..note(0, "Battery low", Priority::Normal)
+            },
+            start + SECOND,
+        );
+        notifier.push(note(1, "!", Priority::Urgent), start);
+
+        // count the panel writes the daemon makes when it skips frames
+        // equal to the one last drawn
+        let mut drawn = [None, None];
+        let mut writes = [0, 0, 0];
+        let mut now = start;
+        while now < start + SECOND {
+            for panel in 0..2 {
+                let frame = notifier.frame(panel, now);
+                if frame.is_some() && drawn[panel] != frame {
+                    drawn[panel] = frame;
+                    writes[panel] += 1;
+                }
+            }
+            now += Duration::from_millis(50);
+        }
+        // static text is sent once, the urgent edges blink twice a second
+        assert_eq!(writes[0], 1);
+        assert_eq!(writes[1], 2);
+
+        assert!(notifier.is_animating());
+        while now < start + SECOND * 3 {
+            let frame = notifier.frame(0, now);
+            if drawn[0] != frame {
+                drawn[0] = frame;
+                writes[2] += 1;
+            }
+            now += SCROLL_STEP;
+        }
+        // scrolling text moves by a column every step
+        assert_eq!(
+            writes[2],
+            (SECOND * 2).as_millis() / SCROLL_STEP.as_millis() + 1
+        );
+    }
+}
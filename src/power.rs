@@ -0,0 +1,95 @@
+use std::time::{Duration, Instant};
+
+use crate::config::{PowerProfileConfig, PowerSource};
+
+/// How often the battery state is polled. Charge changes slowly and the
+/// `battery` crate walks sysfs on every query, so every frame is overkill.
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Power source and combined charge of all batteries.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PowerState {
+    pub source: PowerSource,
+    pub charge_pct: f32,
+}
+
+/// Read the current power state, `None` on machines without a battery.
+pub fn read_power_state() -> Option<PowerState> {
+    let batteries = battery::Manager::new().ok()?.batteries().ok()?;
+
+    let mut energy = 0.0;
+    let mut energy_full = 0.0;
+    let mut discharging = false;
+    let mut found = false;
+    for battery_dev in batteries.flatten() {
+        found = true;
+        energy += battery_dev
+            .energy()
+            .get::<battery::units::energy::watt_hour>();
+        energy_full += battery_dev
+            .energy_full()
+            .get::<battery::units::energy::watt_hour>();
+        discharging |= battery_dev.state() == battery::State::Discharging;
+    }
+
+    if !found {
+        return None;
+    }
+
+    Some(PowerState {
+        source: if discharging {
+            PowerSource::Battery
+        } else {
+            PowerSource::Ac
+        },
+        charge_pct: if energy_full > 0.0 {
+            energy / energy_full * 100.0
+        } else {
+            0.0
+        },
+    })
+}
+
+/// Whether `profile` applies to the given power state.
+pub fn profile_matches(profile: &PowerProfileConfig, state: &PowerState) -> bool {
+    let source_ok = profile.power.is_none_or(|source| source == state.source);
+    let charge_ok = profile.below.is_none_or(|below| state.charge_pct < below);
+    source_ok && charge_ok
+}
+
+/// Cached power state that refreshes itself at most every `POLL_INTERVAL`.
+pub struct PowerMonitor {
+    state: Option<PowerState>,
+    last_poll: Option<Instant>,
+}
+
+impl PowerMonitor {
+    pub fn new() -> Self {
+        Self {
+            state: None,
+            last_poll: None,
+        }
+    }
+
+    pub fn state(&mut self) -> Option<PowerState> {
+        if self
+            .last_poll
+            .is_none_or(|last| last.elapsed() >= POLL_INTERVAL)
+        {
+            self.state = read_power_state();
+            self.last_poll = Some(Instant::now());
+        }
+        self.state
+    }
+
+    /// First profile matching the current power state.
+    pub fn active<'a>(
+        &mut self,
+        profiles: &'a [PowerProfileConfig],
+    ) -> Option<&'a PowerProfileConfig> {
+        let state = self.state()?;
+        profiles
+            .iter()
+            .find(|profile| profile_matches(profile, &state))
+    }
+}
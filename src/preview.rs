@@ -0,0 +1,196 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+    thread,
+    time::Duration,
+};
+
+use clap::{Args, ValueEnum};
+
+use crate::{
+    config::Config,
+    daemon::{self, WidgetList},
+    matrix::{self, Matrix, MATRIX_HEIGHT, MATRIX_WIDTH},
+    metrics::{Metrics, MetricsHandle},
+};
+
+/// Grey of an LED that is off, so the dot grid stays visible.
+const OFF_GREY: f32 = 28.0;
+
+/// Render the configured widgets without panels attached.
+#[derive(Args)]
+pub struct PreviewArgs {
+    /// Number of frames to render
+    #[arg(long, default_value_t = 1)]
+    ticks: usize,
+
+    /// Delay between frames in milliseconds, defaults to `update_interval`
+    #[arg(long)]
+    interval: Option<u64>,
+
+    /// Render a named layout instead of the default widgets
+    #[arg(long)]
+    layout: Option<String>,
+
+    /// Number of panels, defaults to the highest panel a widget is placed on
+    #[arg(long)]
+    panels: Option<usize>,
+
+    #[arg(long, value_enum, default_value_t = PreviewFormat::Ascii)]
+    format: PreviewFormat,
+
+    /// Directory the image files are written to
+    #[arg(long, default_value = ".")]
+    output: PathBuf,
+
+    /// Image pixels per LED
+    #[arg(long, default_value_t = 8)]
+    scale: usize,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum PreviewFormat {
+    /// Text art on stdout, `#` is lit, `+` is dim and `.` is off
+    Ascii,
+    Pgm,
+    Png,
+}
+
+/// Rasterize a frame with one round dot per LED, `scale` pixels apart.
+fn rasterize(frame: &Matrix, scale: usize) -> (usize, usize, Vec<u8>) {
+    let (width, height) = (MATRIX_WIDTH * scale, MATRIX_HEIGHT * scale);
+    let mut pixels = vec![0; width * height];
+    let radius = scale as f32 * 0.4;
+    let center = (scale as f32 - 1.0) / 2.0;
+
+    for (y, row) in frame.iter().enumerate() {
+        for (x, &level) in row.iter().enumerate() {
+            // perceptual levels are compressed so dim LEDs stay visible
+            let grey = OFF_GREY + (255.0 - OFF_GREY) * (level as f32 / 255.0).sqrt();
+            for dy in 0..scale {
+                for dx in 0..scale {
+                    let distance = (dx as f32 - center).hypot(dy as f32 - center);
+                    if distance <= radius {
+                        pixels[(y * scale + dy) * width + x * scale + dx] = grey.round() as u8;
+                    }
+                }
+            }
+        }
+    }
+
+    (width, height, pixels)
+}
+
+fn write_image(
+    path: &Path,
+    format: PreviewFormat,
+    frame: &Matrix,
+    scale: usize,
+) -> Result<(), String> {
+    let (width, height, pixels) = rasterize(frame, scale);
+    let file =
+        File::create(path).map_err(|err| format!("unable to create {}: {err}", path.display()))?;
+    let mut out = BufWriter::new(file);
+
+    match format {
+        PreviewFormat::Pgm => {
+            write!(out, "P5\n{width} {height}\n255\n")
+                .and_then(|_| out.write_all(&pixels))
+                .map_err(|err| format!("unable to write {}: {err}", path.display()))?;
+        }
+        PreviewFormat::Png => {
+            let mut encoder = png::Encoder::new(out, width as u32, height as u32);
+            encoder.set_color(png::ColorType::Grayscale);
+            encoder.set_depth(png::BitDepth::Eight);
+            encoder
+                .write_header()
+                .and_then(|mut writer| writer.write_image_data(&pixels))
+                .map_err(|err| format!("unable to write {}: {err}", path.display()))?;
+        }
+        PreviewFormat::Ascii => {}
+    }
+    Ok(())
+}
+
+/// Print the panels of one tick next to each other.
+fn print_ascii(tick: usize, frames: &[Matrix]) {
+    println!("tick {tick}");
+    let panels: Vec<Vec<String>> = frames.iter().map(matrix::to_text_art).collect();
+    for row in 0..MATRIX_HEIGHT {
+        let line: Vec<&str> = panels.iter().map(|panel| panel[row].as_str()).collect();
+        println!("{}", line.join("  "));
+    }
+    println!();
+}
+
+fn load(
+    args: &PreviewArgs,
+    config: &Config,
+    metrics: &MetricsHandle,
+) -> Result<(WidgetList, usize), String> {
+    let configs = match &args.layout {
+        Some(name) => config
+            .layouts
+            .get(name)
+            .ok_or_else(|| format!("unknown layout \"{name}\""))?,
+        None => &config.widgets,
+    };
+    let panels = args.panels.unwrap_or_else(|| {
+        configs
+            .iter()
+            .map(|widget| widget.panel + 1)
+            .max()
+            .unwrap_or(1)
+    });
+
+    let widgets = daemon::load_widgets(configs, metrics);
+    daemon::validate_widget_placements(&widgets, panels)?;
+    Ok((widgets, panels))
+}
+
+/// Run the widgets for the requested ticks and write every composed frame.
+pub fn run(args: PreviewArgs, config: &Config) -> Result<(), String> {
+    if args.scale == 0 {
+        return Err("scale must be greater than 0".to_string());
+    }
+    let metrics = MetricsHandle::new(Metrics::new(
+        config.general.history_length,
+        &config.general.hwmon,
+    ));
+    let (mut widgets, panels) = load(&args, config, &metrics)?;
+    if args.format != PreviewFormat::Ascii {
+        std::fs::create_dir_all(&args.output)
+            .map_err(|err| format!("unable to create {}: {err}", args.output.display()))?;
+    }
+    let interval = Duration::from_millis(args.interval.unwrap_or(config.general.update_interval));
+
+    for tick in 0..args.ticks {
+        if tick > 0 {
+            thread::sleep(interval);
+        }
+        metrics.sample();
+
+        let frames: Vec<Matrix> = (0..panels)
+            .map(|panel| daemon::compose(&mut widgets, panel))
+            .collect();
+
+        let extension = match args.format {
+            PreviewFormat::Ascii => {
+                print_ascii(tick, &frames);
+                continue;
+            }
+            PreviewFormat::Pgm => "pgm",
+            PreviewFormat::Png => "png",
+        };
+        for (panel, frame) in frames.iter().enumerate() {
+            let path = args
+                .output
+                .join(format!("panel{panel}-{tick:03}.{extension}"));
+            write_image(&path, args.format, frame, args.scale)?;
+            log::info!("wrote {}", path.display());
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,169 @@
+use std::cmp::Ordering;
+
+use chrono::{Datelike, NaiveDateTime, NaiveTime, Weekday};
+
+use crate::config::{DisplayOverride, ScheduleConfig};
+
+/// A parsed `[[schedules]]` entry.
+pub struct Schedule {
+    start: NaiveTime,
+    end: NaiveTime,
+    days: Vec<Weekday>,
+    pub apply: DisplayOverride,
+}
+
+impl Schedule {
+    pub fn from_config(config: &ScheduleConfig) -> Result<Self, String> {
+        let start = parse_time(&config.start)?;
+        let end = parse_time(&config.end)?;
+
+        let mut days = Vec::with_capacity(config.days.len());
+        for day in config.days.iter() {
+            let weekday = day
+                .parse::<Weekday>()
+                .map_err(|_| format!("invalid schedule weekday \"{day}\""))?;
+            days.push(weekday);
+        }
+
+        Ok(Self {
+            start,
+            end,
+            days,
+            apply: config.apply.clone(),
+        })
+    }
+
+    fn runs_on(&self, day: Weekday) -> bool {
+        self.days.is_empty() || self.days.contains(&day)
+    }
+
+    /// Check whether `now` (local wall clock time) falls inside this window.
+    pub fn is_active(&self, now: NaiveDateTime) -> bool {
+        let time = now.time();
+        let today = now.weekday();
+
+        match self.start.cmp(&self.end) {
+            Ordering::Less => self.runs_on(today) && time >= self.start && time < self.end,
+            Ordering::Greater => {
+                // wraps past midnight, the early part belongs to yesterday
+                (self.runs_on(today) && time >= self.start)
+                    || (self.runs_on(today.pred()) && time < self.end)
+            }
+            Ordering::Equal => self.runs_on(today),
+        }
+    }
+}
+
+fn parse_time(value: &str) -> Result<NaiveTime, String> {
+    NaiveTime::parse_from_str(value, "%H:%M")
+        .map_err(|err| format!("invalid schedule time \"{value}\" (expected HH:MM): {err}"))
+}
+
+/// Parse every configured schedule, keeping config order.
+pub fn load(configs: &[ScheduleConfig]) -> Result<Vec<Schedule>, String> {
+    configs.iter().map(Schedule::from_config).collect()
+}
+
+/// Return the first schedule that covers `now`.
+pub fn active(schedules: &[Schedule], now: NaiveDateTime) -> Option<&Schedule> {
+    schedules.iter().find(|schedule| schedule.is_active(now))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+
+    fn schedule(start: &str, end: &str, days: &[&str]) -> Schedule {
+        Schedule::from_config(&ScheduleConfig {
+            start: start.to_string(),
+            end: end.to_string(),
+            days: days.iter().map(|day| day.to_string()).collect(),
+            apply: DisplayOverride::default(),
+        })
+        .unwrap()
+    }
+
+    fn at(day: Weekday, time: &str) -> NaiveDateTime {
+        NaiveDate::from_isoywd_opt(2024, 10, day)
+            .unwrap()
+            .and_time(parse_time(time).unwrap())
+    }
+
+    #[test]
+    fn overnight_window_belongs_to_its_start_day() {
+        let night = schedule("23:00", "07:00", &["Mon"]);
+        for (day, time, active) in [
+            (Weekday::Mon, "22:59", false),
+            (Weekday::Mon, "23:00", true),
+            (Weekday::Mon, "23:59", true),
+            (Weekday::Tue, "00:00", true),
+            (Weekday::Tue, "06:59", true),
+            (Weekday::Tue, "07:00", false),
+            (Weekday::Mon, "06:59", false),
+            (Weekday::Tue, "23:00", false),
+        ] {
+            assert_eq!(night.is_active(at(day, time)), active, "{day} {time}");
+        }
+    }
+
+    #[test]
+    fn end_is_exclusive() {
+        let lunch = schedule("12:00", "13:00", &[]);
+        for (time, active) in [
+            ("11:59", false),
+            ("12:00", true),
+            ("12:59", true),
+            ("13:00", false),
+        ] {
+            assert_eq!(lunch.is_active(at(Weekday::Sun, time)), active, "{time}");
+        }
+    }
+
+    #[test]
+    fn equal_bounds_cover_the_whole_day() {
+        let weekend = schedule("08:00", "08:00", &["Sat", "Sun"]);
+        for (day, time, active) in [
+            (Weekday::Sat, "00:00", true),
+            (Weekday::Sat, "07:59", true),
+            (Weekday::Sun, "23:59", true),
+            (Weekday::Fri, "08:00", false),
+            (Weekday::Mon, "00:00", false),
+        ] {
+            assert_eq!(weekend.is_active(at(day, time)), active, "{day} {time}");
+        }
+    }
+
+    #[test]
+    fn invalid_entries_are_rejected() {
+        for (start, end, day) in [
+            ("25:00", "07:00", "Mon"),
+            ("23:00", "7", "Mon"),
+            ("23:00", "07:00", "Funday"),
+        ] {
+            let config = ScheduleConfig {
+                start: start.to_string(),
+                end: end.to_string(),
+                days: vec![day.to_string()],
+                apply: DisplayOverride::default(),
+            };
+            assert!(
+                Schedule::from_config(&config).is_err(),
+                "{start} {end} {day}"
+            );
+        }
+    }
+
+    #[test]
+    fn first_matching_schedule_wins() {
+        let schedules = [
+            schedule("22:00", "06:00", &[]),
+            schedule("00:00", "00:00", &[]),
+        ];
+        let night = active(&schedules, at(Weekday::Wed, "01:00")).unwrap();
+        assert!(std::ptr::eq(night, &schedules[0]));
+        let day = active(&schedules, at(Weekday::Wed, "12:00")).unwrap();
+        assert!(std::ptr::eq(day, &schedules[1]));
+    }
+}
@@ -0,0 +1,51 @@
+//! A private `dbus-daemon` for the tests of the D-Bus integrations.
+
+use std::{
+    io::{BufRead, BufReader},
+    path::PathBuf,
+    process::{Child, Command, Stdio},
+};
+
+/// A session bus daemon listening on a socket in the temp directory, killed
+/// again when dropped.
+pub struct TestBus {
+    daemon: Child,
+    socket: PathBuf,
+    pub address: String,
+}
+
+impl TestBus {
+    /// Start a bus for the test `name`. Panics when `dbus-daemon` is not
+    /// installed, a missing bus must not let the D-Bus tests pass unseen.
+    pub fn start(name: &str) -> Self {
+        let socket = std::env::temp_dir().join(format!("bus-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_file(&socket);
+        let mut daemon = Command::new("dbus-daemon")
+            .args(["--session", "--nofork", "--print-address"])
+            .arg(format!("--address=unix:path={}", socket.display()))
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .expect("dbus-daemon is needed to run the D-Bus tests");
+
+        // the address is printed once the socket is listening
+        let mut address = String::new();
+        let stdout = daemon.stdout.take().expect("piped stdout");
+        BufReader::new(stdout)
+            .read_line(&mut address)
+            .expect("dbus-daemon prints its address");
+        Self {
+            daemon,
+            socket,
+            address: address.trim().to_string(),
+        }
+    }
+}
+
+impl Drop for TestBus {
+    fn drop(&mut self) {
+        let _ = self.daemon.kill();
+        let _ = self.daemon.wait();
+        let _ = std::fs::remove_file(&self.socket);
+    }
+}
@@ -1,71 +1,143 @@
-use super::{Shape, Widget, OFF, ON_DIM};
+use crate::config::{BatteryMode, WidgetBatterySetup};
+
+use super::{
+    font::{self, GLYPH_HEIGHT},
+    source::{BatterySource, BatteryStatus, ChargeState},
+    write_bar_1l, Shape, Widget, OFF, ON_DIM, ON_FULL,
+};
 
 /// Create a widget that displays the battery remaining in the laptop
 pub struct BatteryWidget {
     matrix: Vec<u8>,
     shape: Shape,
-    chrg_ind: bool,
+    /// Flips every update, drives the charging and low battery blinks.
+    blink_on: bool,
+    setup: WidgetBatterySetup,
+    source: Box<dyn BatterySource>,
 }
 
 impl BatteryWidget {
-    pub fn new() -> Self {
+    pub fn new(setup: &WidgetBatterySetup, source: Box<dyn BatterySource>) -> Self {
+        let rows = match setup.mode {
+            BatteryMode::Level => 2,
+            // hours above minutes, separated by an empty row
+            BatteryMode::Time => 2 * GLYPH_HEIGHT + 1,
+            BatteryMode::Power | BatteryMode::Health => 1,
+        };
         Self {
             matrix: vec![],
-            chrg_ind: false,
-            shape: Shape { x: 9, y: 2 },
+            blink_on: false,
+            shape: Shape { x: 9, y: rows },
+            setup: setup.clone(),
+            source,
         }
     }
 
-    fn read_status(&self) -> Option<(f32, bool)> {
-        let mut batteries = battery::Manager::new().ok()?.batteries().ok()?;
-        let battery_dev = batteries.next()?.ok()?;
-
-        let bat_level_pct = battery_dev
-            .state_of_charge()
-            .get::<battery::units::ratio::percent>();
-        let is_charging = battery_dev.state() == battery::State::Charging;
-
-        Some((bat_level_pct, is_charging))
-    }
-}
-
-impl Widget for BatteryWidget {
-    fn update(&mut self) {
-        // recreate matrix
+    fn draw_level(&mut self, status: &BatteryStatus) {
         let width = self.get_shape().x;
-        let height = self.get_shape().y;
-        debug_assert_eq!(height, 2, "battery renderer paints two horizontal rows");
-        self.matrix = vec![OFF; width * height];
+        let (bat_level_pct, state) = (status.percent, status.state);
 
-        let Some((bat_level_pct, is_charging)) = self.read_status() else {
+        // a low battery blinks as a whole
+        let low = self.setup.low.is_some_and(|low| bat_level_pct <= low);
+        if low && state == ChargeState::Discharging && !self.blink_on {
             return;
-        };
+        }
 
         let num_illum = (bat_level_pct * ((width * 2) - 1) as f32 / 100.0).round();
 
         let row_1 = ((num_illum / 2.0 + 0.5) as usize).min(width.saturating_sub(1));
         let row_2 = ((num_illum / 2.0) as usize).min(width.saturating_sub(1));
 
-        // draw battery bar
+        // draw battery bar, bright once full
+        let level = if state == ChargeState::Full {
+            ON_FULL
+        } else {
+            ON_DIM
+        };
         for i in 0..width {
             if i <= row_1 {
-                self.matrix[i] = ON_DIM;
+                self.matrix[i] = level;
             }
             if i <= row_2 {
-                self.matrix[self.shape.x + i] = ON_DIM;
+                self.matrix[self.shape.x + i] = level;
             }
         }
 
-        // draw charging indicator
-        if is_charging && bat_level_pct < 99.0 {
-            if row_1 > row_2 {
-                self.matrix[row_1] = if self.chrg_ind { ON_DIM } else { OFF };
-            } else {
-                self.matrix[self.shape.x + row_2] = if self.chrg_ind { ON_DIM } else { OFF };
+        // the tip of the bar blinks while charging and stays lit while the
+        // charger holds the battery
+        let tip = if row_1 > row_2 {
+            row_1
+        } else {
+            self.shape.x + row_2
+        };
+        match state {
+            ChargeState::Charging if bat_level_pct < 99.0 => {
+                self.matrix[tip] = if self.blink_on { ON_DIM } else { OFF };
             }
+            ChargeState::NotCharging => self.matrix[tip] = ON_FULL,
+            _ => {}
+        }
+    }
+
+    fn draw_time(&mut self, status: &BatteryStatus) {
+        let width = self.get_shape().x;
+        let (hours, minutes) = match status.time_remaining {
+            Some(time) => {
+                let minutes = time.as_secs() / 60;
+                (
+                    format!("{:02}", (minutes / 60).min(99)),
+                    format!("{:02}", minutes % 60),
+                )
+            }
+            None => ("--".to_string(), "--".to_string()),
+        };
+        let level = if status.state == ChargeState::Charging {
+            ON_DIM
+        } else {
+            ON_FULL
+        };
+
+        for (idx, text) in [hours, minutes].iter().enumerate() {
+            let columns = font::text_columns(text);
+            font::draw_columns(
+                &mut self.matrix,
+                width,
+                1,
+                idx * (GLYPH_HEIGHT + 1),
+                columns.len(),
+                &columns,
+                0,
+                level,
+            );
+        }
+    }
+}
+
+impl Widget for BatteryWidget {
+    fn update(&mut self) {
+        // recreate matrix
+        let width = self.get_shape().x;
+        let height = self.get_shape().y;
+        self.matrix = vec![OFF; width * height];
 
-            self.chrg_ind = !self.chrg_ind;
+        let status = self.source.status();
+        if let Some(status) = status {
+            match self.setup.mode {
+                BatteryMode::Level => self.draw_level(&status),
+                BatteryMode::Time => self.draw_time(&status),
+                BatteryMode::Power => write_bar_1l(
+                    &mut self.matrix,
+                    0,
+                    width,
+                    status.power,
+                    self.setup.max_power,
+                ),
+                BatteryMode::Health => {
+                    write_bar_1l(&mut self.matrix, 0, width, status.health, 100.0)
+                }
+            }
         }
+        self.blink_on = !self.blink_on;
     }
 
     fn get_matrix(&self) -> &[u8] {
@@ -76,3 +148,95 @@ impl Widget for BatteryWidget {
         &self.shape
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::widget::snapshot::{assert_snapshot, FixedBattery};
+
+    fn setup(mode: BatteryMode) -> WidgetBatterySetup {
+        WidgetBatterySetup {
+            mode,
+            max_power: 60.0,
+            low: Some(10.0),
+        }
+    }
+
+    fn status(percent: f32, state: ChargeState) -> BatteryStatus {
+        BatteryStatus {
+            percent,
+            state,
+            power: 12.0,
+            health: 88.0,
+            time_remaining: Some(Duration::from_secs(3 * 3600 + 25 * 60)),
+        }
+    }
+
+    fn widget(mode: BatteryMode, status: BatteryStatus) -> BatteryWidget {
+        BatteryWidget::new(&setup(mode), Box::new(FixedBattery(Some(status))))
+    }
+
+    #[test]
+    fn discharging() {
+        let mut widget = widget(BatteryMode::Level, status(40.0, ChargeState::Discharging));
+        widget.update();
+        assert_snapshot("battery_discharging", &widget);
+    }
+
+    #[test]
+    fn charging_blinks() {
+        let mut widget = widget(BatteryMode::Level, status(75.0, ChargeState::Charging));
+        widget.update();
+        assert_snapshot("battery_charging_off", &widget);
+        widget.update();
+        assert_snapshot("battery_charging_on", &widget);
+    }
+
+    #[test]
+    fn full_and_not_charging() {
+        let mut full = widget(BatteryMode::Level, status(100.0, ChargeState::Full));
+        full.update();
+        assert_snapshot("battery_full", &full);
+
+        let mut held = widget(BatteryMode::Level, status(80.0, ChargeState::NotCharging));
+        held.update();
+        assert_snapshot("battery_not_charging", &held);
+    }
+
+    #[test]
+    fn low_battery_blinks() {
+        let mut widget = widget(BatteryMode::Level, status(8.0, ChargeState::Discharging));
+        widget.update();
+        assert!(widget.get_matrix().iter().all(|&level| level == OFF));
+        widget.update();
+        assert_snapshot("battery_low", &widget);
+    }
+
+    #[test]
+    fn time_remaining() {
+        let mut widget = widget(BatteryMode::Time, status(60.0, ChargeState::Discharging));
+        widget.update();
+        assert_snapshot("battery_time", &widget);
+    }
+
+    #[test]
+    fn power_and_health() {
+        let mut power = widget(BatteryMode::Power, status(60.0, ChargeState::Discharging));
+        power.update();
+        assert_snapshot("battery_power", &power);
+
+        let mut health = widget(BatteryMode::Health, status(60.0, ChargeState::Discharging));
+        health.update();
+        assert_snapshot("battery_health", &health);
+    }
+
+    #[test]
+    fn no_battery() {
+        let mut widget =
+            BatteryWidget::new(&setup(BatteryMode::Level), Box::new(FixedBattery(None)));
+        widget.update();
+        assert_snapshot("battery_missing", &widget);
+    }
+}
@@ -1,6 +1,20 @@
-use chrono::{Local, Timelike};
+use std::cmp::Ordering;
 
-use super::{Shape, Widget, OFF, ON_DIM, ON_FULL};
+use chrono::{DateTime, Datelike, FixedOffset, Timelike, Weekday};
+
+use crate::{
+    config::{ClockSeconds, ClockStyle, ClockView, WidgetClockSetup},
+    matrix::MATRIX_HEIGHT,
+};
+
+use super::{
+    font::{self, GLYPH_HEIGHT},
+    source::{ClockSource, SystemClock, ZonedClock},
+    write_bar_1l, write_bar_vertical, Shape, Widget, OFF, ON_DIM, ON_FULL, ON_MIN,
+};
+
+/// Side of the analog dial.
+const DIAL_SIZE: usize = 9;
 
 const DIGIT_0: &[u8] = [
     OFF, ON_FULL, OFF, ON_FULL, OFF, ON_FULL, ON_FULL, OFF, ON_FULL, ON_FULL, OFF, ON_FULL, OFF,
@@ -65,14 +79,50 @@ const DIGIT_9: &[u8] = [
 pub struct ClockWidget {
     matrix: Vec<u8>,
     shape: Shape,
+    setup: WidgetClockSetup,
+    /// Flips every update, drives the blinking colon.
+    colon_on: bool,
+    clock: Box<dyn ClockSource>,
 }
 
 impl ClockWidget {
-    /// Construct a digital clock widget in HH:MM 24-hour format.
-    pub fn new() -> Self {
+    /// Construct a digital clock widget, HH:MM in 24-hour format by default.
+    pub fn new(setup: &WidgetClockSetup) -> Self {
+        Self::with_zoned_clock(setup, Box::new(SystemClock))
+    }
+
+    /// Construct the widget around a custom time source shown in the
+    /// configured time zone.
+    pub fn with_zoned_clock(setup: &WidgetClockSetup, clock: Box<dyn ClockSource>) -> Self {
+        // validated with the config, an unknown zone falls back to local time
+        let zone = setup
+            .timezone
+            .as_ref()
+            .and_then(|timezone| timezone.parse().ok());
+        let clock: Box<dyn ClockSource> = match zone {
+            Some(zone) => Box::new(ZonedClock::new(zone, clock)),
+            None => clock,
+        };
+        Self::with_clock(setup, clock)
+    }
+
+    /// Construct the widget around a custom time source.
+    pub fn with_clock(setup: &WidgetClockSetup, clock: Box<dyn ClockSource>) -> Self {
+        let rows = match (setup.style, setup.view, setup.seconds) {
+            (ClockStyle::Digital, ClockView::Time, ClockSeconds::Off) => 11,
+            // seconds get a row below an empty one
+            (ClockStyle::Digital, ClockView::Time, _) => 13,
+            (ClockStyle::Digital, ClockView::Date, _) => 17,
+            (ClockStyle::Fill, _, _) => MATRIX_HEIGHT,
+            (ClockStyle::Analog, _, _) => DIAL_SIZE,
+            (ClockStyle::Day, _, _) => 24,
+        };
         Self {
-            shape: Shape { x: 9, y: 11 },
+            shape: Shape { x: 9, y: rows },
             matrix: Vec::new(),
+            setup: setup.clone(),
+            colon_on: true,
+            clock,
         }
     }
 
@@ -95,13 +145,15 @@ impl ClockWidget {
         }
     }
 
-    fn render_number(num: u32) -> Vec<u8> {
+    /// Render a two digit number, `pad` keeps a leading zero.
+    fn render_number(num: u32, pad: bool) -> Vec<u8> {
         let mut numrow = vec![0; 9 * 5];
         let first_digit = Self::render_digit(num / 10);
         let second_digit = Self::render_digit(num % 10);
+        let show_first = pad || num >= 10;
         for idx in 0..(9 * 5) {
             let cell = match idx % 9 {
-                1..=3 => first_digit[((idx / 9) * 3) + (idx % 9) - 1],
+                1..=3 if show_first => first_digit[((idx / 9) * 3) + (idx % 9) - 1],
                 5..=7 => second_digit[((idx / 9) * 3) + idx % 9 - 5],
                 _ => OFF,
             };
@@ -109,15 +161,158 @@ impl ClockWidget {
         }
         numrow
     }
+
+    /// Row between hours and minutes holding the colon and AM/PM dot.
+    fn render_separator(&mut self, hour: u32) -> [u8; 9] {
+        let mut row = [OFF; 9];
+        if self.setup.blink_colon {
+            if self.colon_on {
+                row[3] = ON_DIM;
+                row[5] = ON_DIM;
+            }
+            self.colon_on = !self.colon_on;
+        }
+        if self.setup.twelve_hour {
+            row[if hour < 12 { 0 } else { 8 }] = ON_FULL;
+        }
+        row
+    }
+
+    fn render_seconds(&self, second: u32) -> [u8; 9] {
+        let mut row = [OFF; 9];
+        match self.setup.seconds {
+            ClockSeconds::Off => {}
+            ClockSeconds::Bar => write_bar_1l(&mut row, 0, 9, second as f32, 60.0),
+            ClockSeconds::Dots => row[second as usize * 9 / 60] = ON_FULL,
+        }
+        row
+    }
+
+    fn render_weekday(weekday: Weekday) -> Vec<u8> {
+        let name = match weekday {
+            Weekday::Mon => "MO",
+            Weekday::Tue => "TU",
+            Weekday::Wed => "WE",
+            Weekday::Thu => "TH",
+            Weekday::Fri => "FR",
+            Weekday::Sat => "SA",
+            Weekday::Sun => "SU",
+        };
+        let columns = font::text_columns(name);
+        let mut rows = vec![OFF; 9 * GLYPH_HEIGHT];
+        font::draw_columns(&mut rows, 9, 1, 0, columns.len(), &columns, 0, ON_FULL);
+        rows
+    }
+
+    fn render_digital(&mut self, time: &DateTime<FixedOffset>) {
+        self.matrix = Vec::with_capacity(9 * self.shape.y);
+
+        match self.setup.view {
+            ClockView::Time => {
+                let hour = match self.setup.twelve_hour {
+                    true => (time.hour() + 11) % 12 + 1,
+                    false => time.hour(),
+                };
+                let separator = self.render_separator(time.hour());
+                self.matrix
+                    .extend(Self::render_number(hour, !self.setup.twelve_hour));
+                self.matrix.extend(separator);
+                self.matrix.extend(Self::render_number(time.minute(), true));
+                if self.setup.seconds != ClockSeconds::Off {
+                    self.matrix.extend([OFF; 9]);
+                    self.matrix.extend(self.render_seconds(time.second()));
+                }
+            }
+            ClockView::Date => {
+                self.matrix.extend(Self::render_number(time.day(), true));
+                self.matrix.extend([OFF; 9]);
+                self.matrix.extend(Self::render_number(time.month(), true));
+                self.matrix.extend([OFF; 9]);
+                self.matrix.extend(Self::render_weekday(time.weekday()));
+            }
+        }
+    }
+
+    /// Day on the left four columns, hour on the right four.
+    fn render_fill(&mut self, day: f32, hour: f32) {
+        let (width, height) = (self.shape.x, self.shape.y);
+        for x in 0..4 {
+            write_bar_vertical(&mut self.matrix, width, x, height, day, 1.0);
+            write_bar_vertical(&mut self.matrix, width, width - 1 - x, height, hour, 1.0);
+        }
+    }
+
+    /// Index of the dial LED `distance` from the centre towards `turns`,
+    /// counted clockwise from 12 o'clock.
+    fn dial_index(turns: f32, distance: f32) -> usize {
+        let center = (DIAL_SIZE / 2) as f32;
+        let angle = turns * std::f32::consts::TAU;
+        let x = (center + distance * angle.sin()).round() as usize;
+        let y = (center - distance * angle.cos()).round() as usize;
+        y * DIAL_SIZE + x
+    }
+
+    fn render_analog(&mut self, hour: u32, minute: u32) {
+        let radius = (DIAL_SIZE / 2) as f32;
+
+        // marks at 12, 3, 6 and 9 o'clock
+        for quarter in 0..4 {
+            self.matrix[Self::dial_index(quarter as f32 / 4.0, radius)] = ON_MIN;
+        }
+
+        // the long minute hand is dim, the short hour hand bright
+        let minutes = minute as f32 / 60.0;
+        let hours = ((hour % 12) as f32 + minutes) / 12.0;
+        for (turns, length, level) in [(minutes, radius, ON_DIM), (hours, radius * 0.6, ON_FULL)] {
+            let mut distance = 0.0;
+            while distance <= length {
+                self.matrix[Self::dial_index(turns, distance)] = level;
+                distance += 0.5;
+            }
+        }
+    }
+
+    /// A row per hour, full for passed hours and filling for the current one.
+    /// Working hours get a faint first column.
+    fn render_day(&mut self, hour: u32, minute: u32) {
+        let width = self.shape.x;
+        for row in 0..24 {
+            let position = row * width;
+            if let Some([start, end]) = self.setup.work_hours {
+                if (start..end).contains(&(row as u32)) {
+                    self.matrix[position] = ON_MIN;
+                }
+            }
+            match (row as u32).cmp(&hour) {
+                Ordering::Less => self.matrix[position + 1..position + width].fill(ON_DIM),
+                Ordering::Equal => write_bar_1l(
+                    &mut self.matrix,
+                    position + 1,
+                    width - 1,
+                    minute as f32,
+                    60.0,
+                ),
+                Ordering::Greater => {}
+            }
+        }
+    }
 }
 
 impl Widget for ClockWidget {
     fn update(&mut self) {
-        let time = Local::now();
-        self.matrix = Vec::with_capacity(9 * 11);
-        self.matrix.extend(Self::render_number(time.hour()));
-        self.matrix.extend([OFF; 9]);
-        self.matrix.extend(Self::render_number(time.minute()));
+        let time = self.clock.now();
+        self.matrix = vec![OFF; self.shape.x * self.shape.y];
+
+        match self.setup.style {
+            ClockStyle::Digital => self.render_digital(&time),
+            ClockStyle::Fill => {
+                let day = time.num_seconds_from_midnight() as f32 / 86400.0;
+                let hour = (time.minute() * 60 + time.second()) as f32 / 3600.0;
+                self.render_fill(day, hour);
+            }
+            ClockStyle::Analog => self.render_analog(time.hour(), time.minute()),
+            ClockStyle::Day => self.render_day(time.hour(), time.minute()),
+        }
     }
 
     fn get_matrix(&self) -> &[u8] {
@@ -128,3 +323,121 @@ impl Widget for ClockWidget {
         &self.shape
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::widget::snapshot::{assert_snapshot, FixedClock};
+
+    fn widget(setup: WidgetClockSetup, hour: u32, minute: u32, second: u32) -> ClockWidget {
+        let mut widget =
+            ClockWidget::with_clock(&setup, Box::new(FixedClock::at(hour, minute, second)));
+        widget.update();
+        widget
+    }
+
+    #[test]
+    fn morning() {
+        assert_snapshot("clock_0941", &widget(WidgetClockSetup::default(), 9, 41, 0));
+    }
+
+    #[test]
+    fn before_midnight() {
+        assert_snapshot(
+            "clock_2358",
+            &widget(WidgetClockSetup::default(), 23, 58, 59),
+        );
+    }
+
+    #[test]
+    fn twelve_hour_with_seconds_bar() {
+        let setup = WidgetClockSetup {
+            twelve_hour: true,
+            seconds: ClockSeconds::Bar,
+            ..Default::default()
+        };
+        assert_snapshot("clock_12h_pm_bar", &widget(setup, 21, 5, 30));
+    }
+
+    #[test]
+    fn blinking_colon_and_seconds_dot() {
+        let setup = WidgetClockSetup {
+            blink_colon: true,
+            seconds: ClockSeconds::Dots,
+            ..Default::default()
+        };
+        let mut widget = widget(setup, 0, 7, 45);
+        assert_snapshot("clock_colon_on", &widget);
+        widget.update();
+        assert_snapshot("clock_colon_off", &widget);
+    }
+
+    #[test]
+    fn date() {
+        let setup = WidgetClockSetup {
+            view: ClockView::Date,
+            ..Default::default()
+        };
+        // the fixed clock stands on Thursday, March 14th
+        assert_snapshot("clock_date", &widget(setup, 12, 0, 0));
+    }
+
+    #[test]
+    fn fill() {
+        let setup = WidgetClockSetup {
+            style: ClockStyle::Fill,
+            ..Default::default()
+        };
+        assert_snapshot("clock_fill_1815", &widget(setup, 18, 15, 0));
+    }
+
+    #[test]
+    fn analog() {
+        let setup = WidgetClockSetup {
+            style: ClockStyle::Analog,
+            ..Default::default()
+        };
+        assert_snapshot("clock_analog_0941", &widget(setup.clone(), 9, 41, 0));
+        assert_snapshot("clock_analog_1500", &widget(setup, 15, 0, 0));
+    }
+
+    #[test]
+    fn day_with_work_hours() {
+        let setup = WidgetClockSetup {
+            style: ClockStyle::Day,
+            work_hours: Some([9, 17]),
+            ..Default::default()
+        };
+        assert_snapshot("clock_day_1430", &widget(setup, 14, 30, 0));
+    }
+
+    #[test]
+    fn invalid_time_zone_rejected() {
+        let config = |timezone: &str| {
+            toml::from_str::<crate::config::Config>(&format!(
+                "[general]\nbrightness = 100\n\n[[widgets]]\npanel = 0\nx = 0\ny = 0\n[widgets.setup.Clock]\ntimezone = \"{timezone}\"\n"
+            ))
+            .unwrap()
+        };
+        assert!(crate::config::validate(&config("America/New_York")).is_ok());
+        let err = crate::config::validate(&config("Mars/Olympus_Mons")).unwrap_err();
+        assert!(err.starts_with("clock widget timezone"), "{err}");
+    }
+
+    #[test]
+    fn time_zone() {
+        // 12:00 in UTC+1 is 07:00 in New York, on daylight saving time by
+        // the 14th of March
+        let setup = WidgetClockSetup {
+            timezone: Some("America/New_York".to_string()),
+            ..Default::default()
+        };
+        let mut zoned = ClockWidget::with_zoned_clock(&setup, Box::new(FixedClock::at(12, 0, 0)));
+        zoned.update();
+        assert_eq!(
+            zoned.get_matrix(),
+            widget(WidgetClockSetup::default(), 7, 0, 0).get_matrix()
+        );
+        assert_snapshot("clock_new_york_0700", &zoned);
+    }
+}
@@ -1,49 +1,123 @@
-use super::{write_bar_1l, Shape, Widget, OFF, ON_FULL};
+use std::collections::HashMap;
+
+use crate::config::{CpuGroup, CpuMode, WidgetCpuSetup};
+
+use super::{
+    scale_level,
+    source::{CpuLocation, CpuSource},
+    write_bar_1l, write_bar_vertical, Shape, Widget, OFF, ON_FULL, ON_MIN,
+};
 
 /// Create a widget that displays the usage of all CPU cores, one per row.
 pub struct CpuWidget {
+    /// Usage, or frequency in percent of the maximum, of every logical CPU.
     cpu_usages: Vec<u8>,
+    mode: CpuMode,
     merge_threads: bool,
-    sys: sysinfo::System,
+    /// Logical CPUs averaged into each column or heatmap LED.
+    groups: Vec<Vec<usize>>,
+    source: Box<dyn CpuSource>,
     matrix: Vec<u8>,
     shape: Shape,
 }
 
+/// Logical CPUs of every group, ordered by their first CPU.
+fn group_cpus(topology: &[CpuLocation], group: CpuGroup) -> Vec<Vec<usize>> {
+    let mut groups: Vec<Vec<usize>> = Vec::new();
+    let mut index = HashMap::new();
+    for (cpu, location) in topology.iter().enumerate() {
+        let key = match group {
+            CpuGroup::Core => (location.package, location.core),
+            CpuGroup::Ccd => (location.package, location.cache),
+            CpuGroup::All => (0, 0),
+        };
+        let slot = *index.entry(key).or_insert_with(|| {
+            groups.push(Vec::new());
+            groups.len() - 1
+        });
+        groups[slot].push(cpu);
+    }
+    groups
+}
+
+/// Spread `groups` over at most `width` columns, neighbouring groups share
+/// a column when there are more groups than columns.
+fn fold_columns(groups: Vec<Vec<usize>>, width: usize) -> Vec<Vec<usize>> {
+    if groups.len() <= width {
+        return groups;
+    }
+    let count = groups.len();
+    let mut columns = vec![Vec::new(); width];
+    for (idx, cpus) in groups.into_iter().enumerate() {
+        columns[idx * width / count].extend(cpus);
+    }
+    columns
+}
+
 impl CpuWidget {
-    pub fn new(merge_threads: bool) -> Self {
-        let mut sys = sysinfo::System::new();
-        sys.refresh_cpu_all();
-        let num_cpus = sys.cpus().len();
+    pub fn new(setup: &WidgetCpuSetup, mut source: Box<dyn CpuSource>) -> Self {
+        let num_cpus = source.usages().len();
+        let groups = group_cpus(&source.topology(), setup.group);
+        let (shape, groups) = match setup.mode {
+            CpuMode::Heatmap => (
+                Shape {
+                    x: 9,
+                    y: groups.len().div_ceil(9).max(1),
+                },
+                groups,
+            ),
+            CpuMode::Total => (Shape { x: 9, y: 1 }, Vec::new()),
+            CpuMode::Bars | CpuMode::Frequency if setup.merge_threads => (
+                Shape {
+                    x: 9,
+                    y: setup.height,
+                },
+                fold_columns(groups, 9),
+            ),
+            CpuMode::Bars | CpuMode::Frequency => (Shape { x: 9, y: num_cpus }, Vec::new()),
+        };
 
         Self {
-            shape: match merge_threads {
-                false => Shape { x: 9, y: num_cpus },
-                true => Shape { x: 9, y: 8 },
-            },
+            shape,
             cpu_usages: vec![0; num_cpus],
-            merge_threads,
-            sys,
+            mode: setup.mode,
+            merge_threads: setup.merge_threads,
+            groups,
+            source,
             matrix: Vec::new(),
         }
     }
 
+    /// Average of the logical CPUs in `cpus`.
+    fn average(&self, cpus: &[usize]) -> f32 {
+        let sum: u32 = cpus
+            .iter()
+            .map(|&cpu| u32::from(self.cpu_usages.get(cpu).copied().unwrap_or(0)))
+            .sum();
+        sum as f32 / cpus.len().max(1) as f32
+    }
+
     fn draw_merged_threads(&mut self, width: usize, height: usize) {
-        for idy in 0..height {
-            let inverse_y = height - (idy + 1);
-            for (idx, chunk) in self.cpu_usages.chunks(2).enumerate() {
-                if idx >= width {
-                    break;
-                }
-                let sum: u16 = chunk.iter().map(|&usage| u16::from(usage)).sum();
-                let usage = sum as f32 / chunk.len() as f32;
-
-                if usage >= (inverse_y * 10) as f32 {
-                    self.matrix[(idy * width) + idx] = ON_FULL;
-                }
-            }
+        for idx in 0..self.groups.len() {
+            let usage = self.average(&self.groups[idx]);
+            write_bar_vertical(&mut self.matrix, width, idx, height, usage, 100.0);
         }
     }
 
+    fn draw_heatmap(&mut self) {
+        for idx in 0..self.groups.len() {
+            // idle groups keep a faint LED so the block keeps its shape
+            let usage = self.average(&self.groups[idx]);
+            self.matrix[idx] = scale_level(ON_FULL, usage / 100.0).max(ON_MIN);
+        }
+    }
+
+    fn draw_total(&mut self, width: usize) {
+        let cpus: Vec<usize> = (0..self.cpu_usages.len()).collect();
+        let usage = self.average(&cpus);
+        write_bar_1l(&mut self.matrix, 0, width, usage, 100.0);
+    }
+
     fn draw_per_core_bars(&mut self, width: usize, height: usize) {
         for row in 0..height {
             let usage = self.cpu_usages.get(row).copied().unwrap_or(0) as f32;
@@ -54,11 +128,20 @@ impl CpuWidget {
 
 impl Widget for CpuWidget {
     fn update(&mut self) {
-        // refresh the cpu usage
-        self.sys.refresh_cpu_all();
-
-        for (idx, usage) in self.sys.cpus().iter().enumerate() {
-            self.cpu_usages[idx] = usage.cpu_usage().round() as u8;
+        // refresh the cpu usage, or the frequency in percent of the maximum
+        let values: Vec<f32> = match self.mode {
+            CpuMode::Frequency => self
+                .source
+                .frequencies()
+                .into_iter()
+                .map(|freq| freq.current / freq.max.max(1.0) * 100.0)
+                .collect(),
+            _ => self.source.usages(),
+        };
+        for (idx, value) in values.into_iter().enumerate() {
+            if let Some(slot) = self.cpu_usages.get_mut(idx) {
+                *slot = value.clamp(0.0, 100.0).round() as u8;
+            }
         }
 
         // recreate matrix
@@ -66,10 +149,13 @@ impl Widget for CpuWidget {
         let height = self.get_shape().y;
         self.matrix = vec![OFF; width * height];
 
-        if self.merge_threads {
-            self.draw_merged_threads(width, height);
-        } else {
-            self.draw_per_core_bars(width, height);
+        match self.mode {
+            CpuMode::Heatmap => self.draw_heatmap(),
+            CpuMode::Total => self.draw_total(width),
+            CpuMode::Bars | CpuMode::Frequency if self.merge_threads => {
+                self.draw_merged_threads(width, height)
+            }
+            CpuMode::Bars | CpuMode::Frequency => self.draw_per_core_bars(width, height),
         }
     }
 
@@ -81,3 +167,146 @@ impl Widget for CpuWidget {
         &self.shape
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::widget::{
+        snapshot::{assert_snapshot, FixedCpu},
+        source::CpuFrequency,
+    };
+
+    fn setup(merge_threads: bool, group: CpuGroup, height: usize) -> WidgetCpuSetup {
+        WidgetCpuSetup {
+            merge_threads,
+            mode: CpuMode::Bars,
+            group,
+            height,
+        }
+    }
+
+    /// Fixed usages with CPUs running at the given MHz out of 4000.
+    struct ClockedCpu(FixedCpu, Vec<f32>);
+
+    impl CpuSource for ClockedCpu {
+        fn usages(&mut self) -> Vec<f32> {
+            self.0.usages()
+        }
+
+        fn topology(&mut self) -> Vec<CpuLocation> {
+            self.0.topology()
+        }
+
+        fn frequencies(&mut self) -> Vec<CpuFrequency> {
+            self.1
+                .iter()
+                .map(|&current| CpuFrequency {
+                    current,
+                    max: 4000.0,
+                })
+                .collect()
+        }
+    }
+
+    #[test]
+    fn per_core_bars() {
+        let mut widget = CpuWidget::new(
+            &setup(false, CpuGroup::Core, 8),
+            Box::new(FixedCpu(vec![0.0, 12.5, 50.0, 100.0])),
+        );
+        widget.update();
+        assert_snapshot("cpu_per_core", &widget);
+    }
+
+    #[test]
+    fn merged_threads() {
+        let usages = vec![10.0, 30.0, 50.0, 70.0, 90.0, 100.0, 0.0, 0.0];
+        let mut widget =
+            CpuWidget::new(&setup(true, CpuGroup::Core, 8), Box::new(FixedCpu(usages)));
+        widget.update();
+        assert_snapshot("cpu_merged_threads", &widget);
+    }
+
+    #[test]
+    fn ccd_columns_with_custom_height() {
+        // 32 threads, 16 cores over 2 caches
+        let usages = (0..32)
+            .map(|cpu| if cpu < 16 { 80.0 } else { 20.0 })
+            .collect();
+        let mut widget = CpuWidget::new(&setup(true, CpuGroup::Ccd, 5), Box::new(FixedCpu(usages)));
+        widget.update();
+        assert_snapshot("cpu_ccd_height5", &widget);
+    }
+
+    #[test]
+    fn more_cores_than_columns() {
+        // 16 cores fold into 9 columns
+        let usages = (0..32).map(|cpu| (cpu / 2 * 6) as f32).collect();
+        let mut widget =
+            CpuWidget::new(&setup(true, CpuGroup::Core, 8), Box::new(FixedCpu(usages)));
+        widget.update();
+        assert_snapshot("cpu_folded_columns", &widget);
+    }
+
+    #[test]
+    fn heatmap_of_sixteen_cores() {
+        let usages = (0..32).map(|cpu| (cpu / 2 * 7) as f32).collect();
+        let setup = WidgetCpuSetup {
+            mode: CpuMode::Heatmap,
+            ..setup(false, CpuGroup::Core, 8)
+        };
+        let mut widget = CpuWidget::new(&setup, Box::new(FixedCpu(usages)));
+        widget.update();
+        assert_snapshot("cpu_heatmap", &widget);
+    }
+
+    #[test]
+    fn total_bar() {
+        let setup = WidgetCpuSetup {
+            mode: CpuMode::Total,
+            ..setup(false, CpuGroup::Core, 8)
+        };
+        let usages = vec![100.0, 50.0, 25.0, 0.0];
+        let mut widget = CpuWidget::new(&setup, Box::new(FixedCpu(usages)));
+        widget.update();
+        assert_snapshot("cpu_total", &widget);
+    }
+
+    #[test]
+    fn frequency_columns() {
+        let setup = WidgetCpuSetup {
+            mode: CpuMode::Frequency,
+            ..setup(true, CpuGroup::Core, 8)
+        };
+        let clocks = vec![4000.0, 3600.0, 2000.0, 1800.0, 800.0, 800.0];
+        let mut widget = CpuWidget::new(
+            &setup,
+            Box::new(ClockedCpu(FixedCpu(vec![100.0; 6]), clocks)),
+        );
+        widget.update();
+        assert_snapshot("cpu_frequency_columns", &widget);
+    }
+
+    #[test]
+    fn siblings_follow_topology() {
+        // SMT siblings numbered apart like on most x86 machines
+        let topology: Vec<CpuLocation> = (0..8)
+            .map(|cpu| CpuLocation {
+                package: 0,
+                core: cpu % 4,
+                cache: 0,
+            })
+            .collect();
+        assert_eq!(
+            group_cpus(&topology, CpuGroup::Core),
+            vec![vec![0, 4], vec![1, 5], vec![2, 6], vec![3, 7]]
+        );
+        assert_eq!(
+            group_cpus(&topology, CpuGroup::All),
+            vec![(0..8).collect::<Vec<_>>()]
+        );
+        let folded = fold_columns((0..12).map(|group| vec![group]).collect(), 9);
+        assert_eq!(folded.len(), 9);
+        assert_eq!(folded.concat(), (0..12).collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,181 @@
+use crate::config::{FanStyle, WidgetFanSetup};
+
+use super::{
+    sensor_matches,
+    source::{FanReading, FanSource, SystemFans},
+    write_bar_1l, Shape, Widget, OFF, ON_DIM, ON_FULL,
+};
+
+/// Size of the propeller drawn by the spin style.
+const PROPELLER_SIZE: usize = 5;
+
+/// Propeller blades of every frame as (x, y) cells, turning clockwise in
+/// steps of 45 degrees. The centre is drawn separately.
+const PROPELLER_FRAMES: [[(usize, usize); 4]; 4] = [
+    [(2, 0), (2, 1), (2, 3), (2, 4)],
+    [(4, 0), (3, 1), (1, 3), (0, 4)],
+    [(0, 2), (1, 2), (3, 2), (4, 2)],
+    [(0, 0), (1, 1), (3, 3), (4, 4)],
+];
+
+/// Create a widget that displays the speed of each fan as a bar or a
+/// spinning propeller.
+pub struct FanWidget {
+    source: Box<dyn FanSource>,
+    setup: WidgetFanSetup,
+    /// Propeller position of every fan in frames, advanced by its speed.
+    angles: Vec<f32>,
+    matrix: Vec<u8>,
+    shape: Shape,
+}
+
+impl FanWidget {
+    pub fn new(setup: &WidgetFanSetup) -> Self {
+        Self::with_source(setup, Box::new(SystemFans::new(&setup.hwmon)))
+    }
+
+    /// Construct the widget around a custom fan source.
+    pub fn with_source(setup: &WidgetFanSetup, source: Box<dyn FanSource>) -> Self {
+        let rows = match setup.style {
+            FanStyle::Bar => setup.max_fans,
+            // propellers are separated by an empty row
+            FanStyle::Spin => setup.max_fans * (PROPELLER_SIZE + 1) - 1,
+        };
+        Self {
+            source,
+            setup: setup.clone(),
+            angles: vec![0.0; setup.max_fans],
+            matrix: Vec::new(),
+            shape: Shape { x: 9, y: rows },
+        }
+    }
+
+    fn draw_propeller(&mut self, idx: usize, rpm: f32) {
+        let width = self.get_shape().x;
+        let left = (width - PROPELLER_SIZE) / 2;
+        let top = idx * (PROPELLER_SIZE + 1);
+
+        // at most one frame per update, faster steps would look like the
+        // propeller turning backwards
+        let angle = &mut self.angles[idx];
+        *angle = (*angle + (rpm / self.setup.max_rpm).clamp(0.0, 1.0)) % 4.0;
+        let frame = &PROPELLER_FRAMES[*angle as usize];
+
+        let level = if rpm > 0.0 { ON_FULL } else { ON_DIM };
+        for &(x, y) in frame {
+            self.matrix[(top + y) * width + left + x] = level;
+        }
+        self.matrix[(top + PROPELLER_SIZE / 2) * width + left + PROPELLER_SIZE / 2] = ON_FULL;
+    }
+}
+
+impl Widget for FanWidget {
+    fn update(&mut self) {
+        let fans: Vec<FanReading> = self
+            .source
+            .fans()
+            .into_iter()
+            .filter(|fan| {
+                self.setup.fans.is_empty()
+                    || self
+                        .setup
+                        .fans
+                        .iter()
+                        .any(|pattern| sensor_matches(pattern, &fan.chip, &fan.label))
+            })
+            .collect();
+
+        // recreate matrix
+        let width = self.get_shape().x;
+        let height = self.get_shape().y;
+        self.matrix = vec![OFF; width * height];
+
+        // fans beyond the configured ones are left out
+        for (idx, fan) in fans.iter().take(self.setup.max_fans).enumerate() {
+            match self.setup.style {
+                FanStyle::Bar => write_bar_1l(
+                    &mut self.matrix,
+                    idx * width,
+                    width,
+                    fan.rpm,
+                    self.setup.max_rpm,
+                ),
+                FanStyle::Spin => self.draw_propeller(idx, fan.rpm),
+            }
+        }
+    }
+
+    fn get_matrix(&self) -> &[u8] {
+        &self.matrix
+    }
+
+    fn get_shape(&self) -> &Shape {
+        &self.shape
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::widget::snapshot::{assert_snapshot, TempDir};
+
+    fn setup(hwmon: &TempDir, style: FanStyle) -> WidgetFanSetup {
+        WidgetFanSetup {
+            fans: Vec::new(),
+            style,
+            max_rpm: 6000.0,
+            max_fans: 2,
+            hwmon: hwmon.path().to_string_lossy().into_owned(),
+        }
+    }
+
+    #[test]
+    fn bars_from_hwmon() {
+        let hwmon = TempDir::hwmon(
+            "fan-bars",
+            &[
+                ("k10temp", &[("temp1_input", "48000")]),
+                ("cros_ec", &[("fan1_input", "4500"), ("fan2_input", "1500")]),
+            ],
+        );
+        let mut widget = FanWidget::new(&setup(&hwmon, FanStyle::Bar));
+        widget.update();
+        assert_snapshot("fan_bars", &widget);
+    }
+
+    #[test]
+    fn propellers_turn_with_speed() {
+        let hwmon = TempDir::hwmon(
+            "fan-spin",
+            &[("cros_ec", &[("fan1_input", "6000"), ("fan2_input", "0")])],
+        );
+        let mut widget = FanWidget::new(&setup(&hwmon, FanStyle::Spin));
+        widget.update();
+        assert_snapshot("fan_spin_first", &widget);
+        widget.update();
+        assert_snapshot("fan_spin_second", &widget);
+    }
+
+    #[test]
+    fn filtered_by_label() {
+        let hwmon = TempDir::hwmon(
+            "fan-filter",
+            &[(
+                "cros_ec",
+                &[
+                    ("fan1_input", "3000"),
+                    ("fan2_input", "6000"),
+                    ("fan2_label", "right"),
+                ],
+            )],
+        );
+        let setup = WidgetFanSetup {
+            fans: vec!["cros_ec:right".to_string()],
+            ..setup(&hwmon, FanStyle::Bar)
+        };
+        let mut widget = FanWidget::new(&setup);
+        widget.update();
+        assert_eq!(&widget.get_matrix()[..9], &[ON_FULL; 9]);
+        assert_eq!(&widget.get_matrix()[9..], &[OFF; 9]);
+    }
+}
@@ -0,0 +1,137 @@
+//! A 3x5 pixel font for short messages on the 9 column panel.
+
+/// Height of every glyph in rows.
+pub const GLYPH_HEIGHT: usize = 5;
+/// Width of every glyph in columns.
+pub const GLYPH_WIDTH: usize = 3;
+
+/// Rows of a glyph from the top, the three low bits are the columns with
+/// the most significant one on the left.
+fn glyph(character: char) -> [u8; GLYPH_HEIGHT] {
+    match character.to_ascii_uppercase() {
+        'A' => [0b010, 0b101, 0b111, 0b101, 0b101],
+        'B' => [0b110, 0b101, 0b110, 0b101, 0b110],
+        'C' => [0b011, 0b100, 0b100, 0b100, 0b011],
+        'D' => [0b110, 0b101, 0b101, 0b101, 0b110],
+        'E' => [0b111, 0b100, 0b110, 0b100, 0b111],
+        'F' => [0b111, 0b100, 0b110, 0b100, 0b100],
+        'G' => [0b011, 0b100, 0b101, 0b101, 0b011],
+        'H' => [0b101, 0b101, 0b111, 0b101, 0b101],
+        'I' => [0b111, 0b010, 0b010, 0b010, 0b111],
+        'J' => [0b001, 0b001, 0b001, 0b101, 0b010],
+        'K' => [0b101, 0b101, 0b110, 0b101, 0b101],
+        'L' => [0b100, 0b100, 0b100, 0b100, 0b111],
+        'M' => [0b101, 0b111, 0b111, 0b101, 0b101],
+        'N' => [0b110, 0b101, 0b101, 0b101, 0b101],
+        'O' => [0b010, 0b101, 0b101, 0b101, 0b010],
+        'P' => [0b110, 0b101, 0b110, 0b100, 0b100],
+        'Q' => [0b010, 0b101, 0b101, 0b110, 0b011],
+        'R' => [0b110, 0b101, 0b110, 0b101, 0b101],
+        'S' => [0b011, 0b100, 0b010, 0b001, 0b110],
+        'T' => [0b111, 0b010, 0b010, 0b010, 0b010],
+        'U' => [0b101, 0b101, 0b101, 0b101, 0b111],
+        'V' => [0b101, 0b101, 0b101, 0b101, 0b010],
+        'W' => [0b101, 0b101, 0b111, 0b111, 0b101],
+        'X' => [0b101, 0b101, 0b010, 0b101, 0b101],
+        'Y' => [0b101, 0b101, 0b010, 0b010, 0b010],
+        'Z' => [0b111, 0b001, 0b010, 0b100, 0b111],
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b110, 0b001, 0b010, 0b100, 0b111],
+        '3' => [0b110, 0b001, 0b010, 0b001, 0b110],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b110, 0b001, 0b110],
+        '6' => [0b011, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b010, 0b010, 0b010],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b110],
+        ' ' => [0b000, 0b000, 0b000, 0b000, 0b000],
+        '.' => [0b000, 0b000, 0b000, 0b000, 0b010],
+        ',' => [0b000, 0b000, 0b000, 0b010, 0b100],
+        ':' => [0b000, 0b010, 0b000, 0b010, 0b000],
+        ';' => [0b000, 0b010, 0b000, 0b010, 0b100],
+        '!' => [0b010, 0b010, 0b010, 0b000, 0b010],
+        '\'' => [0b010, 0b010, 0b000, 0b000, 0b000],
+        '"' => [0b101, 0b101, 0b000, 0b000, 0b000],
+        '-' => [0b000, 0b000, 0b111, 0b000, 0b000],
+        '+' => [0b000, 0b010, 0b111, 0b010, 0b000],
+        '=' => [0b000, 0b111, 0b000, 0b111, 0b000],
+        '_' => [0b000, 0b000, 0b000, 0b000, 0b111],
+        '/' => [0b001, 0b001, 0b010, 0b100, 0b100],
+        '\\' => [0b100, 0b100, 0b010, 0b001, 0b001],
+        '(' => [0b001, 0b010, 0b010, 0b010, 0b001],
+        ')' => [0b100, 0b010, 0b010, 0b010, 0b100],
+        '<' => [0b001, 0b010, 0b100, 0b010, 0b001],
+        '>' => [0b100, 0b010, 0b001, 0b010, 0b100],
+        '%' => [0b101, 0b001, 0b010, 0b100, 0b101],
+        '#' => [0b101, 0b111, 0b101, 0b111, 0b101],
+        '*' => [0b000, 0b101, 0b010, 0b101, 0b000],
+        // anything else, including '?', renders as a question mark
+        _ => [0b110, 0b001, 0b010, 0b000, 0b010],
+    }
+}
+
+/// Render `text` as a strip of columns, each a 5-bit mask with the top row in
+/// the most significant bit. Glyphs are separated by one empty column.
+pub fn text_columns(text: &str) -> Vec<u8> {
+    let mut columns = Vec::new();
+    for (idx, character) in text.chars().enumerate() {
+        if idx > 0 {
+            columns.push(0);
+        }
+
+        let rows = glyph(character);
+        for col in 0..GLYPH_WIDTH {
+            let mut mask = 0;
+            for row in rows.iter() {
+                mask = (mask << 1) | ((row >> (GLYPH_WIDTH - 1 - col)) & 1);
+            }
+            columns.push(mask);
+        }
+    }
+    columns
+}
+
+/// Draw a window of a column strip into a widget matrix.
+///
+/// The strip column at `offset` lands in matrix column `x`; columns outside
+/// the strip stay untouched. `offset` may be negative to scroll text in from
+/// the right.
+#[allow(clippy::too_many_arguments)]
+pub fn draw_columns(
+    mat: &mut [u8],
+    mat_width: usize,
+    x: usize,
+    y: usize,
+    width: usize,
+    columns: &[u8],
+    offset: isize,
+    level: u8,
+) {
+    debug_assert!(
+        x + width <= mat_width,
+        "callers keep the text window inside the matrix row"
+    );
+    debug_assert!(
+        (y + GLYPH_HEIGHT) * mat_width <= mat.len(),
+        "callers allocate the full glyph height below y"
+    );
+    if x + width > mat_width || (y + GLYPH_HEIGHT) * mat_width > mat.len() {
+        return;
+    }
+
+    for col in 0..width {
+        let strip_idx = offset + col as isize;
+        if strip_idx < 0 {
+            continue;
+        }
+        let Some(mask) = columns.get(strip_idx as usize) else {
+            continue;
+        };
+        for row in 0..GLYPH_HEIGHT {
+            if (mask >> (GLYPH_HEIGHT - 1 - row)) & 1 == 1 {
+                mat[(y + row) * mat_width + x + col] = level;
+            }
+        }
+    }
+}
@@ -0,0 +1,215 @@
+use crate::{
+    config::{GraphStyle, WidgetGraphSetup},
+    metrics::{Metric, MetricsHandle},
+};
+
+use super::{write_bar_vertical, Shape, Widget, OFF, ON_DIM, ON_FULL};
+
+/// Create a widget that plots the recent history of a metric, newest sample
+/// in the rightmost column.
+pub struct GraphWidget {
+    metric: Metric,
+    style: GraphStyle,
+    min: f32,
+    max: Option<f32>,
+    threshold: Option<f32>,
+    metrics: MetricsHandle,
+    matrix: Vec<u8>,
+    shape: Shape,
+}
+
+impl GraphWidget {
+    pub fn new(setup: &WidgetGraphSetup, metrics: MetricsHandle) -> Self {
+        Self {
+            metric: setup.metric.clone(),
+            style: setup.style,
+            min: setup.min,
+            max: setup.max,
+            threshold: setup.threshold,
+            metrics,
+            matrix: Vec::new(),
+            shape: Shape {
+                x: setup.width,
+                y: setup.height,
+            },
+        }
+    }
+
+    /// Value at the top of the graph for the samples on screen.
+    fn top(&self, samples: &[f32]) -> f32 {
+        match self.max {
+            Some(max) => max,
+            None if self.metric.is_percent() => 100.0,
+            // rates scale to the busiest sample, an idle graph stays empty
+            None => samples.iter().copied().fold(self.min + 1.0, f32::max),
+        }
+    }
+}
+
+impl Widget for GraphWidget {
+    fn update(&mut self) {
+        let width = self.get_shape().x;
+        let height = self.get_shape().y;
+        self.matrix = vec![OFF; width * height];
+
+        let history = self.metrics.history(&self.metric);
+        let samples = &history[history.len().saturating_sub(width)..];
+        let range = self.top(samples) - self.min;
+        let offset = width - samples.len();
+
+        for (idx, &value) in samples.iter().enumerate() {
+            let x = offset + idx;
+            match self.style {
+                GraphStyle::Bars => {
+                    write_bar_vertical(&mut self.matrix, width, x, height, value - self.min, range);
+                }
+                GraphStyle::Line => {
+                    let fraction = ((value - self.min) / range).clamp(0.0, 1.0);
+                    let row = ((fraction * height as f32) as usize).min(height - 1);
+                    self.matrix[(height - 1 - row) * width + x] = ON_FULL;
+                }
+            }
+        }
+
+        // the threshold only shows where the graph leaves room for it
+        if let Some(threshold) = self.threshold {
+            let fraction = (threshold - self.min) / range;
+            if (0.0..=1.0).contains(&fraction) {
+                let row = ((fraction * height as f32) as usize).min(height - 1);
+                for cell in self.matrix[(height - 1 - row) * width..][..width].iter_mut() {
+                    if *cell == OFF {
+                        *cell = ON_DIM;
+                    }
+                }
+            }
+        }
+    }
+
+    fn get_matrix(&self) -> &[u8] {
+        &self.matrix
+    }
+
+    fn get_shape(&self) -> &Shape {
+        &self.shape
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{
+        metrics::Metrics,
+        widget::{
+            snapshot::{assert_snapshot, FixedBattery, FixedMemory, FixedTemperatures},
+            source::{
+                CpuLocation, CpuSource, InterfaceTraffic, MemoryStats, NetworkSample, NetworkSource,
+            },
+        },
+    };
+
+    /// Reports the next usage of a fixed series on every refresh.
+    struct SeriesCpu(std::vec::IntoIter<f32>);
+
+    impl CpuSource for SeriesCpu {
+        fn usages(&mut self) -> Vec<f32> {
+            vec![self.0.next().unwrap_or(0.0)]
+        }
+
+        fn topology(&mut self) -> Vec<CpuLocation> {
+            vec![CpuLocation {
+                package: 0,
+                core: 0,
+                cache: 0,
+            }]
+        }
+    }
+
+    /// Receives the next byte count of a fixed series on every refresh.
+    struct SeriesNetwork(std::vec::IntoIter<u64>);
+
+    impl NetworkSource for SeriesNetwork {
+        fn sample(&mut self) -> NetworkSample {
+            NetworkSample {
+                elapsed: Duration::from_secs(1),
+                interfaces: vec![InterfaceTraffic {
+                    name: "wlan0".to_string(),
+                    received: self.0.next().unwrap_or(0),
+                    transmitted: 1024,
+                }],
+                default_route: None,
+            }
+        }
+    }
+
+    fn graph(metric: &str, style: GraphStyle, threshold: Option<f32>) -> GraphWidget {
+        let series = vec![
+            0.0, 10.0, 25.0, 40.0, 55.0, 70.0, 85.0, 100.0, 60.0, 30.0, 5.0,
+        ];
+        let metrics = MetricsHandle::new(Metrics::with_sources(
+            16,
+            Box::new(SeriesCpu(series.clone().into_iter())),
+            Box::new(FixedMemory(MemoryStats::default())),
+            Box::new(SeriesNetwork(
+                series
+                    .iter()
+                    .map(|&percent| (percent * 1024.0) as u64)
+                    .collect::<Vec<_>>()
+                    .into_iter(),
+            )),
+            Box::new(FixedBattery(None)),
+            Box::new(FixedTemperatures(Vec::new())),
+        ));
+        let setup = WidgetGraphSetup {
+            metric: metric.parse().unwrap(),
+            style,
+            width: 9,
+            height: 8,
+            min: 0.0,
+            max: None,
+            threshold,
+        };
+
+        let mut widget = GraphWidget::new(&setup, metrics.clone());
+        for _ in 0..10 {
+            widget.update();
+            metrics.sample();
+        }
+        widget.update();
+        widget
+    }
+
+    #[test]
+    fn cpu_bars() {
+        assert_snapshot("graph_cpu_bars", &graph("cpu", GraphStyle::Bars, None));
+    }
+
+    #[test]
+    fn cpu_line_with_threshold() {
+        assert_snapshot(
+            "graph_cpu_line_threshold",
+            &graph("cpu", GraphStyle::Line, Some(80.0)),
+        );
+    }
+
+    #[test]
+    fn rates_scale_to_the_peak() {
+        assert_snapshot("graph_rx_auto", &graph("rx:wlan0", GraphStyle::Bars, None));
+    }
+
+    #[test]
+    fn percent_min_needs_room_below_100() {
+        let config = |metric: &str, range: &str| {
+            toml::from_str::<crate::config::Config>(&format!(
+                "[general]\nbrightness = 100\n\n[[widgets]]\npanel = 0\nx = 0\ny = 0\n[widgets.setup.Graph]\nmetric = \"{metric}\"\n{range}\n"
+            ))
+            .unwrap()
+        };
+        assert!(crate::config::validate(&config("cpu", "min = 50")).is_ok());
+        assert!(crate::config::validate(&config("cpu", "min = 100\nmax = 150")).is_ok());
+        assert!(crate::config::validate(&config("rx:wlan0", "min = 100")).is_ok());
+        let err = crate::config::validate(&config("battery", "min = 100")).unwrap_err();
+        assert!(err.starts_with("graph widget min of battery"), "{err}");
+    }
+}
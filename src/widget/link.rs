@@ -0,0 +1,160 @@
+use crate::config::WidgetLinkSetup;
+
+use super::{
+    network::matches_devices,
+    source::{LinkSource, LinkState, LinkStatus, SystemLinks},
+    write_bar_1l, write_char, Shape, Widget, OFF, ON_DIM, ON_FULL,
+};
+
+/// Create a widget that displays the link state of network interfaces, one
+/// row each. The first LED shows the state, the rest the Wi-Fi signal, a dim
+/// row for wired links, a running dot while connecting and nothing while down.
+pub struct LinkWidget {
+    source: Box<dyn LinkSource>,
+    setup: WidgetLinkSetup,
+    /// Update counter driving the blink patterns.
+    phase: usize,
+    matrix: Vec<u8>,
+    shape: Shape,
+}
+
+impl LinkWidget {
+    pub fn new(setup: &WidgetLinkSetup) -> Self {
+        Self::with_source(setup, Box::new(SystemLinks))
+    }
+
+    /// Construct the widget around a custom link source.
+    pub fn with_source(setup: &WidgetLinkSetup, source: Box<dyn LinkSource>) -> Self {
+        Self {
+            source,
+            setup: setup.clone(),
+            phase: 0,
+            matrix: Vec::new(),
+            shape: Shape {
+                x: 9,
+                y: 1 + setup.max_interfaces,
+            },
+        }
+    }
+
+    fn draw_link(&mut self, position: usize, link: &LinkStatus) {
+        let width = self.get_shape().x;
+        let row = &mut self.matrix[position..position + width];
+
+        match link.state {
+            LinkState::Up => {
+                row[0] = ON_FULL;
+                match link.signal {
+                    Some(signal) => write_bar_1l(row, 1, width - 1, signal, 100.0),
+                    None => row[1..].fill(ON_DIM),
+                }
+            }
+            LinkState::Connecting => {
+                row[0] = ON_DIM;
+                row[1 + self.phase % (width - 1)] = ON_FULL;
+            }
+            LinkState::Down => {
+                row[0] = if self.phase.is_multiple_of(2) {
+                    ON_FULL
+                } else {
+                    OFF
+                };
+            }
+        }
+    }
+}
+
+impl Widget for LinkWidget {
+    fn update(&mut self) {
+        let mut links: Vec<LinkStatus> = self
+            .source
+            .links()
+            .into_iter()
+            .filter(|link| {
+                matches_devices(
+                    &self.setup.devices,
+                    self.setup.exclude.as_deref(),
+                    &link.name,
+                )
+            })
+            .collect();
+        links.sort_by(|a, b| a.name.cmp(&b.name));
+
+        // recreate matrix
+        let width = self.get_shape().x;
+        let height = self.get_shape().y;
+        self.matrix = vec![OFF; width * height];
+
+        // draw header
+        write_char(&mut self.matrix, 0, 'L');
+
+        // interfaces beyond the configured rows are left out
+        for (idx, link) in links.iter().take(height - 1).enumerate() {
+            self.draw_link((1 + idx) * width, link);
+        }
+
+        self.phase = self.phase.wrapping_add(1);
+    }
+
+    fn get_matrix(&self) -> &[u8] {
+        &self.matrix
+    }
+
+    fn get_shape(&self) -> &Shape {
+        &self.shape
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::widget::snapshot::assert_snapshot;
+
+    struct FixedLinks(Vec<LinkStatus>);
+
+    impl LinkSource for FixedLinks {
+        fn links(&mut self) -> Vec<LinkStatus> {
+            self.0.clone()
+        }
+    }
+
+    fn link(name: &str, state: LinkState, signal: Option<f32>) -> LinkStatus {
+        LinkStatus {
+            name: name.to_string(),
+            state,
+            signal,
+        }
+    }
+
+    fn widget(links: Vec<LinkStatus>) -> LinkWidget {
+        let setup = WidgetLinkSetup {
+            devices: Vec::new(),
+            exclude: None,
+            max_interfaces: 3,
+        };
+        LinkWidget::with_source(&setup, Box::new(FixedLinks(links)))
+    }
+
+    #[test]
+    fn wifi_and_wired() {
+        let mut widget = widget(vec![
+            link("wlan0", LinkState::Up, Some(62.0)),
+            link("lo", LinkState::Up, None),
+            link("enp5s0", LinkState::Up, None),
+        ]);
+        widget.update();
+        assert_snapshot("link_wifi_and_wired", &widget);
+    }
+
+    #[test]
+    fn disconnected_and_connecting_blink() {
+        let mut widget = widget(vec![
+            link("enp5s0", LinkState::Down, None),
+            link("wlan0", LinkState::Connecting, None),
+        ]);
+        widget.update();
+        assert_snapshot("link_blink_first", &widget);
+        widget.update();
+        assert_snapshot("link_blink_second", &widget);
+    }
+}
@@ -1,25 +1,85 @@
-use super::{write_bar_1l, write_char, Shape, Widget, OFF};
+use crate::config::{MemoryMode, WidgetMemorySetup};
+
+use super::{
+    source::{MemorySource, MemoryStats},
+    write_bar_1l, write_char, Shape, Widget, OFF, ON_DIM, ON_FULL, ON_MIN,
+};
+
+/// Compression ratio that fills the zram and zswap rows.
+const MAX_COMPRESSION_RATIO: f32 = 4.0;
+
+/// Level of page cache the kernel can't reclaim, between buffers and
+/// available memory.
+const ON_CACHED: u8 = (ON_DIM + ON_MIN) / 2;
 
 /// Create a widget that displays the ram and swap usage
 pub struct MemoryWidget {
-    sys: sysinfo::System,
+    source: Box<dyn MemorySource>,
+    mode: MemoryMode,
+    swap: bool,
+    zram: bool,
+    zswap: bool,
     matrix: Vec<u8>,
     shape: Shape,
 }
 
 impl MemoryWidget {
-    pub fn new() -> Self {
+    pub fn new(setup: &WidgetMemorySetup, source: Box<dyn MemorySource>) -> Self {
         Self {
-            shape: Shape { x: 9, y: 3 },
-            sys: sysinfo::System::new(),
+            shape: Shape {
+                x: 9,
+                y: 2 + usize::from(setup.swap) + usize::from(setup.zram) + usize::from(setup.zswap),
+            },
+            source,
+            mode: setup.mode,
+            swap: setup.swap,
+            zram: setup.zram,
+            zswap: setup.zswap,
             matrix: Vec::new(),
         }
     }
+
+    /// Fill one row with used, buffers, cached and available memory, each LED
+    /// takes the level of the segment covering its center.
+    fn draw_breakdown(&mut self, position: usize, width: usize, memory: &MemoryStats) {
+        if memory.total == 0 {
+            return;
+        }
+
+        // the kernel counts reclaimable cache as available, so only the rest
+        // of the cache and buffers is drawn next to the used memory
+        let available = memory.available.min(memory.total);
+        let in_use = memory.total - available;
+        let reclaimable = available.saturating_sub(memory.free);
+        let cached = memory.cached.saturating_sub(reclaimable).min(in_use);
+        let buffers = memory
+            .buffers
+            .saturating_sub(reclaimable.saturating_sub(memory.cached))
+            .min(in_use - cached);
+        let segments = [
+            (in_use - cached - buffers, ON_FULL),
+            (buffers, ON_DIM),
+            (cached, ON_CACHED),
+            (available, ON_MIN),
+        ];
+
+        for x in 0..width {
+            let center = (x as f32 + 0.5) / width as f32 * memory.total as f32;
+            let mut end = 0.0;
+            for (size, level) in segments {
+                end += size as f32;
+                if center < end {
+                    self.matrix[position + x] = level;
+                    break;
+                }
+            }
+        }
+    }
 }
 
 impl Widget for MemoryWidget {
     fn update(&mut self) {
-        self.sys.refresh_memory();
+        let memory = self.source.memory();
 
         // recreate matrix
         let width = self.get_shape().x;
@@ -30,22 +90,50 @@ impl Widget for MemoryWidget {
         write_char(&mut self.matrix, 0, 'R');
 
         // draw ram usage
-        write_bar_1l(
-            &mut self.matrix,
-            width,
-            width,
-            self.sys.used_memory() as f32,
-            self.sys.total_memory() as f32,
-        );
+        match self.mode {
+            MemoryMode::Usage if memory.total > 0 => write_bar_1l(
+                &mut self.matrix,
+                width,
+                width,
+                memory.used as f32,
+                memory.total as f32,
+            ),
+            MemoryMode::Usage => {}
+            MemoryMode::Breakdown => self.draw_breakdown(width, width, &memory),
+        }
+        let mut row = 2;
 
-        // draw swap usage
-        write_bar_1l(
-            &mut self.matrix,
-            2 * width,
-            width,
-            self.sys.used_swap() as f32,
-            self.sys.total_swap() as f32,
-        );
+        // draw swap usage, machines without swap keep the row dark
+        if self.swap {
+            if memory.total_swap > 0 {
+                write_bar_1l(
+                    &mut self.matrix,
+                    row * width,
+                    width,
+                    memory.used_swap as f32,
+                    memory.total_swap as f32,
+                );
+            }
+            row += 1;
+        }
+
+        // draw how much zram and zswap compress, empty at 1:1 and dark
+        // while unavailable
+        for (enabled, stats) in [(self.zram, memory.zram), (self.zswap, memory.zswap)] {
+            if !enabled {
+                continue;
+            }
+            if let Some(stats) = stats {
+                write_bar_1l(
+                    &mut self.matrix,
+                    row * width,
+                    width,
+                    stats.ratio() - 1.0,
+                    MAX_COMPRESSION_RATIO - 1.0,
+                );
+            }
+            row += 1;
+        }
     }
 
     fn get_matrix(&self) -> &[u8] {
@@ -56,3 +144,111 @@ impl Widget for MemoryWidget {
         &self.shape
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::widget::{
+        snapshot::{assert_snapshot, FixedMemory},
+        source::CompressionStats,
+    };
+
+    const GIB: u64 = 1024 * 1024 * 1024;
+
+    fn stats() -> MemoryStats {
+        MemoryStats {
+            used: 6 * GIB,
+            total: 16 * GIB,
+            available: 10 * GIB,
+            free: 4 * GIB,
+            buffers: GIB,
+            cached: 5 * GIB,
+            used_swap: GIB,
+            total_swap: 8 * GIB,
+            zram: Some(CompressionStats {
+                original: 3 * GIB,
+                used: GIB,
+            }),
+            zswap: Some(CompressionStats {
+                original: 2 * GIB,
+                used: GIB,
+            }),
+        }
+    }
+
+    fn widget(mode: MemoryMode, swap: bool, zram: bool, stats: MemoryStats) -> MemoryWidget {
+        let setup = WidgetMemorySetup {
+            swap,
+            mode,
+            zram,
+            zswap: zram,
+        };
+        let mut widget = MemoryWidget::new(&setup, Box::new(FixedMemory(stats)));
+        widget.update();
+        widget
+    }
+
+    #[test]
+    fn ram_and_swap() {
+        assert_snapshot(
+            "memory_ram_and_swap",
+            &widget(MemoryMode::Usage, true, false, stats()),
+        );
+    }
+
+    #[test]
+    fn swap_row_can_be_hidden() {
+        assert_snapshot(
+            "memory_without_swap",
+            &widget(MemoryMode::Usage, false, false, stats()),
+        );
+    }
+
+    #[test]
+    fn missing_swap_stays_dark() {
+        let stats = MemoryStats {
+            used_swap: 0,
+            total_swap: 0,
+            ..stats()
+        };
+        assert_snapshot(
+            "memory_no_swap_device",
+            &widget(MemoryMode::Usage, true, false, stats),
+        );
+    }
+
+    #[test]
+    fn breakdown_with_zram() {
+        assert_snapshot(
+            "memory_breakdown_zram",
+            &widget(MemoryMode::Breakdown, true, true, stats()),
+        );
+    }
+
+    #[test]
+    fn breakdown_keeps_unreclaimable_cache_apart() {
+        // 3 of the 5 GiB cached are reclaimable, the rest shows next to the
+        // buffers
+        let stats = MemoryStats {
+            used: 11 * GIB,
+            available: 5 * GIB,
+            free: 2 * GIB,
+            ..stats()
+        };
+        assert_snapshot(
+            "memory_breakdown_available",
+            &widget(MemoryMode::Breakdown, false, false, stats),
+        );
+    }
+
+    #[test]
+    fn missing_zswap_stays_dark() {
+        let stats = MemoryStats {
+            zswap: None,
+            ..stats()
+        };
+        let widget = widget(MemoryMode::Usage, true, true, stats);
+        assert_eq!(widget.get_shape().y, 5);
+        assert_eq!(&widget.get_matrix()[4 * 9..], &[OFF; 9]);
+    }
+}
@@ -15,8 +15,33 @@ pub use battery::BatteryWidget;
 pub mod clock;
 pub use clock::ClockWidget;
 
+pub mod stream;
+pub use stream::StreamWidget;
+
+pub mod graph;
+pub use graph::GraphWidget;
+
+pub mod link;
+pub use link::LinkWidget;
+
+pub mod temperature;
+pub use temperature::TemperatureWidget;
+
+pub mod fan;
+pub use fan::FanWidget;
+
+pub mod font;
+pub mod source;
+
+#[cfg(test)]
+pub mod snapshot;
+
+// Widget matrices hold perceptual brightness levels (0=OFF, 255=brightest).
+// `LedMatrix::draw_matrix` maps them through the configured gamma table, so
+// equal steps between these levels should look equally far apart.
 pub const ON_FULL: u8 = 60;
 pub const ON_DIM: u8 = 30;
+pub const ON_MIN: u8 = 10;
 pub const OFF: u8 = 0;
 
 /// Width/height dimensions for a widget matrix.
@@ -33,6 +58,27 @@ pub trait Widget {
     fn get_shape(&self) -> &Shape;
 }
 
+/// Whether `name` matches a glob `pattern` with `*` and `?` wildcards.
+pub fn glob_match(pattern: &str, name: &str) -> bool {
+    fn matches(pattern: &[char], name: &[char]) -> bool {
+        match pattern.split_first() {
+            None => name.is_empty(),
+            Some(('*', rest)) => (0..=name.len()).any(|skip| matches(rest, &name[skip..])),
+            Some((&first, rest)) => name
+                .split_first()
+                .is_some_and(|(&c, tail)| (first == '?' || first == c) && matches(rest, tail)),
+        }
+    }
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    matches(&pattern, &name)
+}
+
+/// Whether a hwmon sensor matches `pattern` by its label or `chip:label`.
+pub fn sensor_matches(pattern: &str, chip: &str, label: &str) -> bool {
+    glob_match(pattern, label) || glob_match(pattern, &format!("{chip}:{label}"))
+}
+
 /// Helper function to draw an ascii character on the led display
 pub fn write_char(mat: &mut [u8], position: usize, character: char) {
     debug_assert!(
@@ -64,6 +110,11 @@ pub fn write_char(mat: &mut [u8], position: usize, character: char) {
     }
 }
 
+/// Scale a perceptual brightness level by `fraction` (clamped to 0.0-1.0).
+pub fn scale_level(level: u8, fraction: f32) -> u8 {
+    (level as f32 * fraction.clamp(0.0, 1.0)).round() as u8
+}
+
 pub fn write_bar_1l(mat: &mut [u8], position: usize, width: usize, value: f32, max: f32) {
     debug_assert!(width > 0, "all widgets define non-zero row width");
     debug_assert!(max > 0.0, "resource capacities are expected to be positive");
@@ -82,7 +133,40 @@ pub fn write_bar_1l(mat: &mut [u8], position: usize, width: usize, value: f32, m
     for x in 0..width {
         match x.cmp(&usage_int) {
             Ordering::Less => mat[position + x] = ON_FULL,
-            Ordering::Equal => mat[position + x] = (ON_FULL as f32 * usage_fract).max(10.0) as u8,
+            Ordering::Equal => mat[position + x] = scale_level(ON_FULL, usage_fract).max(ON_MIN),
+            _ => (),
+        }
+    }
+}
+
+/// Draw a bar growing upwards from the bottom of column `x`, the vertical
+/// counterpart of `write_bar_1l`.
+pub fn write_bar_vertical(
+    mat: &mut [u8],
+    mat_width: usize,
+    x: usize,
+    height: usize,
+    value: f32,
+    max: f32,
+) {
+    debug_assert!(height > 0, "graph widgets define non-zero heights");
+    debug_assert!(
+        x < mat_width && height * mat_width <= mat.len(),
+        "caller draws bars only into the allocated widget matrix"
+    );
+
+    if height == 0 || max <= 0.0 || x >= mat_width || height * mat_width > mat.len() {
+        return;
+    }
+
+    let usage = (value / max).clamp(0.0, 1.0) * height as f32;
+    let usage_int = usage.floor() as usize;
+    let usage_fract = usage - usage_int as f32;
+    for y in 0..height {
+        let position = (height - 1 - y) * mat_width + x;
+        match y.cmp(&usage_int) {
+            Ordering::Less => mat[position] = ON_FULL,
+            Ordering::Equal => mat[position] = scale_level(ON_FULL, usage_fract).max(ON_MIN),
             _ => (),
         }
     }
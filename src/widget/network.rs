@@ -1,87 +1,184 @@
-use std::time::Instant;
+use std::collections::HashMap;
 
-use sysinfo::Networks;
+use crate::config::{WidgetNetworkSetup, DEFAULT_NETWORK_EXCLUDE};
 
-use super::{write_bar_1l, write_char, Shape, Widget, OFF};
+use super::{
+    glob_match,
+    source::{InterfaceTraffic, NetworkSource},
+    write_bar_1l, write_char, Shape, Widget, OFF,
+};
+
+/// Bytes per second in one Mbit/s.
+const MBIT: f32 = 1024.0 * 1024.0 / 8.0;
+
+/// Share of an auto-scaled peak kept per update, halves in about 14 updates.
+const PEAK_DECAY: f32 = 0.95;
+
+/// Lowest auto-scaled full scale so an idle link does not light up on noise.
+const MIN_PEAK: f32 = MBIT;
+
+/// Whether interface `name` matches one of the `devices` globs, or any
+/// interface when there are none, and none of the `exclude` globs. Without
+/// `exclude` the default ones only apply when no `devices` are picked, so
+/// listing an interface like `docker0` shows it.
+pub fn matches_devices(devices: &[String], exclude: Option<&[String]>, name: &str) -> bool {
+    let included = devices.is_empty() || devices.iter().any(|pattern| glob_match(pattern, name));
+    let excluded = match exclude {
+        Some(exclude) => exclude.iter().any(|pattern| glob_match(pattern, name)),
+        None => {
+            devices.is_empty()
+                && DEFAULT_NETWORK_EXCLUDE
+                    .iter()
+                    .any(|pattern| glob_match(pattern, name))
+        }
+    };
+    included && !excluded
+}
+
+/// Full scale of one direction, fixed or following a decaying peak.
+struct Scale {
+    fixed: Option<f32>,
+    peak: f32,
+}
+
+impl Scale {
+    fn new(max_mbit: f32, auto_scale: bool) -> Self {
+        Self {
+            fixed: (!auto_scale).then_some(max_mbit * MBIT),
+            peak: MIN_PEAK,
+        }
+    }
+
+    /// Full scale for a new `rate` in bytes per second.
+    fn update(&mut self, rate: f32) -> f32 {
+        self.fixed.unwrap_or_else(|| {
+            self.peak = (self.peak * PEAK_DECAY).max(rate).max(MIN_PEAK);
+            self.peak
+        })
+    }
+}
+
+/// Download and upload scales of one row pair.
+struct Scales {
+    download: Scale,
+    upload: Scale,
+}
 
 /// Create a widget that displays network download/upload throughput.
 pub struct NetworkWidget {
-    networks: Networks,
-    last_update_time: Instant,
+    source: Box<dyn NetworkSource>,
     matrix: Vec<u8>,
     shape: Shape,
-    devices: Vec<String>,
+    setup: WidgetNetworkSetup,
+    /// Scales by interface name, the summed rows use an empty name.
+    scales: HashMap<String, Scales>,
 }
 
 impl NetworkWidget {
-    pub fn new(devices: &[String]) -> Self {
+    pub fn new(setup: &WidgetNetworkSetup, source: Box<dyn NetworkSource>) -> Self {
+        let pairs = if setup.per_interface {
+            setup.max_interfaces
+        } else {
+            1
+        };
         Self {
-            networks: Networks::new_with_refreshed_list(),
-            last_update_time: Instant::now(),
-            shape: Shape { x: 9, y: 3 },
+            source,
+            shape: Shape {
+                x: 9,
+                y: 1 + 2 * pairs,
+            },
             matrix: Vec::new(),
-            devices: devices.to_vec(),
+            setup: setup.clone(),
+            scales: HashMap::new(),
         }
     }
 
     fn throughput(bytes: u64, elapsed_secs: f32) -> f32 {
         debug_assert!(
             elapsed_secs > 0.0,
-            "update loop tracks elapsed time with monotonic Instant"
+            "network sources measure elapsed time with a monotonic clock"
         );
         let denom = elapsed_secs.max(0.001);
         bytes as f32 / denom
     }
+
+    fn is_selected(&self, name: &str, default_route: Option<&str>) -> bool {
+        let routed = !self.setup.default_route || default_route == Some(name);
+        routed && matches_devices(&self.setup.devices, self.setup.exclude.as_deref(), name)
+    }
+
+    /// Draw the download row at `row` and the upload row below it.
+    fn draw_pair(&mut self, row: usize, key: &str, download: f32, upload: f32) {
+        let width = self.get_shape().x;
+        let setup = &self.setup;
+        let scales = self
+            .scales
+            .entry(key.to_string())
+            .or_insert_with(|| Scales {
+                download: Scale::new(setup.max_download, setup.auto_scale),
+                upload: Scale::new(setup.max_upload, setup.auto_scale),
+            });
+        let rows = [
+            (download, scales.download.update(download)),
+            (upload, scales.upload.update(upload)),
+        ];
+
+        for (offset, (rate, max)) in rows.into_iter().enumerate() {
+            let (value, max) = if self.setup.log_scale {
+                (rate.ln_1p(), max.ln_1p())
+            } else {
+                (rate, max)
+            };
+            write_bar_1l(&mut self.matrix, (row + offset) * width, width, value, max);
+        }
+    }
 }
 
 impl Widget for NetworkWidget {
     fn update(&mut self) {
-        self.networks.refresh();
+        let sample = self.source.sample();
 
         // recreate matrix
         let width = self.get_shape().x;
         let height = self.get_shape().y;
         self.matrix = vec![OFF; width * height];
 
-        // accumulate network traffic
-        // TODO: filter lo, virbr & docker networks
-        let mut download = 0_u64;
-        let total_download = 500u64 * 1024 * 1024 / 8; // 500 mbit/s
-        let mut upload = 0_u64;
-        let total_upload = 100u64 * 1024 * 1024 / 8; // 100 mbit/s
-        for data in self
-            .networks
+        let mut interfaces: Vec<&InterfaceTraffic> = sample
+            .interfaces
             .iter()
-            .filter(|(name, _)| self.devices.contains(name))
-            .map(|(_, data)| data)
-        {
-            download += data.received();
-            upload += data.transmitted();
-        }
+            .filter(|data| self.is_selected(&data.name, sample.default_route.as_deref()))
+            .collect();
+        interfaces.sort_by(|a, b| a.name.cmp(&b.name));
 
-        let elapsed_secs = self.last_update_time.elapsed().as_secs_f32();
-        self.last_update_time = Instant::now();
+        let elapsed_secs = sample.elapsed.as_secs_f32();
 
         // draw header
         write_char(&mut self.matrix, 0, 'N');
 
-        // draw download
-        write_bar_1l(
-            &mut self.matrix,
-            width,
-            width,
-            Self::throughput(download, elapsed_secs),
-            total_download as f32,
-        );
-
-        // draw upload
-        write_bar_1l(
-            &mut self.matrix,
-            2 * width,
-            width,
-            Self::throughput(upload, elapsed_secs),
-            total_upload as f32,
-        );
+        if self.setup.per_interface {
+            // interfaces beyond the configured rows are left out
+            for (pair, data) in interfaces
+                .iter()
+                .take(self.setup.max_interfaces)
+                .enumerate()
+            {
+                self.draw_pair(
+                    1 + 2 * pair,
+                    &data.name,
+                    Self::throughput(data.received, elapsed_secs),
+                    Self::throughput(data.transmitted, elapsed_secs),
+                );
+            }
+        } else {
+            let download = interfaces.iter().map(|data| data.received).sum();
+            let upload = interfaces.iter().map(|data| data.transmitted).sum();
+            self.draw_pair(
+                1,
+                "",
+                Self::throughput(download, elapsed_secs),
+                Self::throughput(upload, elapsed_secs),
+            );
+        }
     }
 
     fn get_matrix(&self) -> &[u8] {
@@ -92,3 +189,136 @@ impl Widget for NetworkWidget {
         &self.shape
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::widget::{
+        snapshot::{assert_snapshot, FixedNetwork},
+        source::NetworkSample,
+    };
+
+    const MIB: u64 = 1024 * 1024;
+
+    fn setup(devices: &[&str]) -> WidgetNetworkSetup {
+        WidgetNetworkSetup {
+            devices: devices.iter().map(|name| name.to_string()).collect(),
+            exclude: None,
+            default_route: false,
+            per_interface: false,
+            max_interfaces: 2,
+            max_download: 500.0,
+            max_upload: 100.0,
+            auto_scale: true,
+            log_scale: false,
+        }
+    }
+
+    /// Fixed traffic with wlan0 as the default route.
+    struct RoutedNetwork(FixedNetwork);
+
+    impl NetworkSource for RoutedNetwork {
+        fn sample(&mut self) -> NetworkSample {
+            NetworkSample {
+                default_route: Some("wlan0".to_string()),
+                ..self.0.sample()
+            }
+        }
+    }
+
+    fn traffic() -> Vec<(&'static str, u64, u64)> {
+        vec![
+            ("wlan0", 25 * MIB, 5 * MIB),
+            ("eth0", 4 * MIB, MIB / 4),
+            ("lo", 500 * MIB, 500 * MIB),
+            ("docker0", 500 * MIB, 500 * MIB),
+        ]
+    }
+
+    #[test]
+    fn selected_devices_only() {
+        let traffic = vec![
+            ("wlan0", 25 * MIB, 5 * MIB),
+            // not listed in the devices, must not count
+            ("eth0", 500 * MIB, 500 * MIB),
+        ];
+        let setup = WidgetNetworkSetup {
+            auto_scale: false,
+            ..setup(&["wlan0"])
+        };
+        let mut widget = NetworkWidget::new(&setup, Box::new(FixedNetwork(traffic)));
+        widget.update();
+        assert_snapshot("network_selected_devices", &widget);
+    }
+
+    #[test]
+    fn idle() {
+        let mut widget = NetworkWidget::new(&setup(&["wlan0"]), Box::new(FixedNetwork(Vec::new())));
+        widget.update();
+        assert_snapshot("network_idle", &widget);
+    }
+
+    #[test]
+    fn rows_per_interface_without_excluded() {
+        let setup = WidgetNetworkSetup {
+            per_interface: true,
+            exclude: Some(vec!["lo".to_string(), "docker*".to_string()]),
+            ..setup(&[])
+        };
+        let mut widget = NetworkWidget::new(&setup, Box::new(FixedNetwork(traffic())));
+        widget.update();
+        assert_snapshot("network_per_interface", &widget);
+    }
+
+    #[test]
+    fn default_route_on_log_scale() {
+        let setup = WidgetNetworkSetup {
+            default_route: true,
+            log_scale: true,
+            auto_scale: false,
+            max_download: 1000.0,
+            max_upload: 1000.0,
+            ..setup(&[])
+        };
+        let mut widget =
+            NetworkWidget::new(&setup, Box::new(RoutedNetwork(FixedNetwork(traffic()))));
+        widget.update();
+        assert_snapshot("network_default_route_log", &widget);
+    }
+
+    #[test]
+    fn auto_scale_decays_after_a_burst() {
+        let mut scale = Scale::new(500.0, true);
+        assert_eq!(scale.update(0.0), MIN_PEAK);
+        assert_eq!(scale.update(100.0 * MBIT), 100.0 * MBIT);
+        assert_eq!(scale.update(0.0), 95.0 * MBIT);
+        for _ in 0..200 {
+            scale.update(0.0);
+        }
+        assert_eq!(scale.update(0.0), MIN_PEAK);
+        assert_eq!(Scale::new(50.0, false).update(100.0 * MBIT), 50.0 * MBIT);
+    }
+
+    #[test]
+    fn default_exclude_only_without_devices() {
+        assert!(!matches_devices(&[], None, "docker0"));
+        assert!(matches_devices(&[], None, "wlan0"));
+        assert!(matches_devices(&["docker0".to_string()], None, "docker0"));
+        let exclude = ["docker*".to_string()];
+        assert!(!matches_devices(
+            &["docker0".to_string()],
+            Some(&exclude),
+            "docker0"
+        ));
+        assert!(matches_devices(&[], Some(&[]), "lo"));
+    }
+
+    #[test]
+    fn globs() {
+        assert!(glob_match("veth*", "veth1a2b"));
+        assert!(glob_match("wlan?", "wlan0"));
+        assert!(glob_match("*", ""));
+        assert!(!glob_match("wlan?", "wlan10"));
+        assert!(!glob_match("docker*", "br-docker0"));
+    }
+}
@@ -0,0 +1,239 @@
+//! Golden snapshots of widget matrices stored as text art.
+//!
+//! Snapshots live in `src/widget/snapshots/<name>.txt`. Lit LEDs use the
+//! `matrix::from_text_art` alphabet (`#` full, `+` dim, `.` off); any other
+//! level is drawn as a letter explained in a legend below the art. Run the
+//! tests with `UPDATE_SNAPSHOTS=1` to write missing or changed snapshots.
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use chrono::{DateTime, FixedOffset, NaiveDate};
+
+use super::{
+    source::{
+        BatterySource, BatteryStatus, ClockSource, CpuLocation, CpuSource, InterfaceTraffic,
+        MemorySource, MemoryStats, NetworkSample, NetworkSource, TemperatureReading,
+        TemperatureSource,
+    },
+    Widget, OFF, ON_DIM, ON_FULL,
+};
+
+/// Draw the current matrix of `widget` as text art with a level legend.
+pub fn render(widget: &dyn Widget) -> String {
+    let shape = widget.get_shape();
+    let cells = widget.get_matrix();
+    assert_eq!(
+        cells.len(),
+        shape.x * shape.y,
+        "matrix does not match the widget shape"
+    );
+
+    let mut legend = BTreeMap::new();
+    for &level in cells {
+        if ![OFF, ON_DIM, ON_FULL].contains(&level) {
+            legend.insert(level, ' ');
+        }
+    }
+    for (slot, letter) in legend.values_mut().zip('a'..='z') {
+        *slot = letter;
+    }
+
+    let mut out = String::new();
+    for row in cells.chunks(shape.x) {
+        for &level in row {
+            out.push(match level {
+                OFF => '.',
+                ON_DIM => '+',
+                ON_FULL => '#',
+                other => legend[&other],
+            });
+        }
+        out.push('\n');
+    }
+    for (level, letter) in legend.iter() {
+        out.push_str(&format!("{letter} = {level}\n"));
+    }
+    out
+}
+
+fn snapshot_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("src/widget/snapshots")
+        .join(format!("{name}.txt"))
+}
+
+/// Compare the widget against the stored snapshot `name`.
+pub fn assert_snapshot(name: &str, widget: &dyn Widget) {
+    let actual = render(widget);
+    let path = snapshot_path(name);
+
+    if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, &actual).unwrap();
+        return;
+    }
+
+    let expected = std::fs::read_to_string(&path).unwrap_or_else(|_| {
+        panic!(
+            "snapshot {} is missing, run with UPDATE_SNAPSHOTS=1 to create it:\n{actual}",
+            path.display()
+        )
+    });
+    if expected != actual {
+        panic!("snapshot {name} differs\n{}", diff(&expected, &actual));
+    }
+}
+
+/// Expected and actual lines next to each other, differing ones marked.
+fn diff(expected: &str, actual: &str) -> String {
+    let expected: Vec<&str> = expected.lines().collect();
+    let actual: Vec<&str> = actual.lines().collect();
+    let width = expected.iter().map(|line| line.len()).max().unwrap_or(0);
+
+    let mut out = format!("  {:width$}   actual\n", "expected");
+    for idx in 0..expected.len().max(actual.len()) {
+        let left = expected.get(idx).copied().unwrap_or("");
+        let right = actual.get(idx).copied().unwrap_or("");
+        let marker = if left == right { ' ' } else { '>' };
+        out.push_str(&format!("{marker} {left:width$}   {right}\n"));
+    }
+    out
+}
+
+/// Reports the same CPU usages on every update, with two SMT threads per
+/// core and four cores per L3 cache.
+pub struct FixedCpu(pub Vec<f32>);
+
+impl CpuSource for FixedCpu {
+    fn usages(&mut self) -> Vec<f32> {
+        self.0.clone()
+    }
+
+    fn topology(&mut self) -> Vec<CpuLocation> {
+        (0..self.0.len() as u32)
+            .map(|cpu| CpuLocation {
+                package: 0,
+                core: cpu / 2,
+                cache: cpu / 8,
+            })
+            .collect()
+    }
+}
+
+pub struct FixedMemory(pub MemoryStats);
+
+impl MemorySource for FixedMemory {
+    fn memory(&mut self) -> MemoryStats {
+        self.0
+    }
+}
+
+/// Moves the same bytes per interface every second.
+pub struct FixedNetwork(pub Vec<(&'static str, u64, u64)>);
+
+impl NetworkSource for FixedNetwork {
+    fn sample(&mut self) -> NetworkSample {
+        NetworkSample {
+            elapsed: Duration::from_secs(1),
+            interfaces: self
+                .0
+                .iter()
+                .map(|&(name, received, transmitted)| InterfaceTraffic {
+                    name: name.to_string(),
+                    received,
+                    transmitted,
+                })
+                .collect(),
+            default_route: None,
+        }
+    }
+}
+
+pub struct FixedBattery(pub Option<BatteryStatus>);
+
+impl BatterySource for FixedBattery {
+    fn status(&mut self) -> Option<BatteryStatus> {
+        self.0
+    }
+}
+
+/// Sensors given as `(chip, label, celsius)`.
+pub struct FixedTemperatures(pub Vec<(&'static str, &'static str, f32)>);
+
+impl TemperatureSource for FixedTemperatures {
+    fn temperatures(&mut self) -> Vec<TemperatureReading> {
+        self.0
+            .iter()
+            .map(|&(chip, label, celsius)| TemperatureReading {
+                chip: chip.to_string(),
+                label: label.to_string(),
+                celsius,
+            })
+            .collect()
+    }
+}
+
+pub struct FixedClock(pub DateTime<FixedOffset>);
+
+impl FixedClock {
+    /// A clock stopped at the given local time in UTC+1.
+    pub fn at(hour: u32, minute: u32, second: u32) -> Self {
+        let time = NaiveDate::from_ymd_opt(2024, 3, 14)
+            .and_then(|date| date.and_hms_opt(hour, minute, second))
+            .expect("valid test time");
+        Self(
+            time.and_local_timezone(FixedOffset::east_opt(3600).unwrap())
+                .unwrap(),
+        )
+    }
+}
+
+impl ClockSource for FixedClock {
+    fn now(&mut self) -> DateTime<FixedOffset> {
+        self.0
+    }
+}
+
+/// A temporary directory removed again when dropped, also when the test
+/// fails.
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    /// Create an empty directory for the test `name`, unique to this process.
+    pub fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&path);
+        std::fs::create_dir_all(&path).expect("create temp dir");
+        Self(path)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+
+    /// Lay out `/sys/class/hwmon` style chips: one `hwmon<N>` directory per
+    /// chip with its `name` and the given attribute files.
+    pub fn hwmon(name: &str, chips: &[(&str, &[(&str, &str)])]) -> Self {
+        let root = Self::new(name);
+        for (idx, (name, files)) in chips.iter().enumerate() {
+            let chip = root.0.join(format!("hwmon{idx}"));
+            std::fs::create_dir_all(&chip).expect("create hwmon chip");
+            std::fs::write(chip.join("name"), format!("{name}\n")).expect("write chip name");
+            for (file, contents) in files.iter() {
+                std::fs::write(chip.join(file), format!("{contents}\n"))
+                    .expect("write chip attribute");
+            }
+        }
+        root
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
@@ -0,0 +1,859 @@
+//! Data the widgets display, behind traits so tests can feed fixed values.
+
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, FixedOffset, Local};
+use chrono_tz::Tz;
+use sysinfo::{Networks, System};
+
+/// Where a logical CPU sits in the processor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CpuLocation {
+    pub package: u32,
+    /// Physical core, shared by SMT siblings and unique within the package.
+    pub core: u32,
+    /// Id of the L3 cache, shared by the cores of one CCD or CCX.
+    pub cache: u32,
+}
+
+/// Clock speed of a logical CPU in MHz.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CpuFrequency {
+    pub current: f32,
+    pub max: f32,
+}
+
+/// Usage of every logical CPU in percent.
+pub trait CpuSource {
+    fn usages(&mut self) -> Vec<f32>;
+
+    /// Location of every logical CPU, in the order of `usages`.
+    fn topology(&mut self) -> Vec<CpuLocation>;
+
+    /// Frequency of every logical CPU in the order of `usages`, empty when
+    /// the source has none.
+    fn frequencies(&mut self) -> Vec<CpuFrequency> {
+        Vec::new()
+    }
+}
+
+/// Memory and swap usage in bytes.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MemoryStats {
+    /// Memory not available to new applications, `total - available`.
+    pub used: u64,
+    pub total: u64,
+    /// `MemAvailable`, free memory plus what the kernel can reclaim.
+    pub available: u64,
+    pub free: u64,
+    pub buffers: u64,
+    pub cached: u64,
+    pub used_swap: u64,
+    pub total_swap: u64,
+    /// Totals over every zram device, `None` without one.
+    pub zram: Option<CompressionStats>,
+    /// The zswap pool, `None` without zswap or access to its debugfs stats.
+    pub zswap: Option<CompressionStats>,
+}
+
+/// Sizes of compressed memory in bytes, from `/sys/block/zram*/mm_stat` or
+/// the zswap debugfs stats.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct CompressionStats {
+    /// Uncompressed size of the stored data.
+    pub original: u64,
+    /// Memory the device uses including allocator overhead.
+    pub used: u64,
+}
+
+impl CompressionStats {
+    /// How many times smaller the data got, 1.0 while the device is empty.
+    pub fn ratio(&self) -> f32 {
+        if self.used == 0 {
+            1.0
+        } else {
+            self.original as f32 / self.used as f32
+        }
+    }
+}
+
+pub trait MemorySource {
+    fn memory(&mut self) -> MemoryStats;
+}
+
+/// Bytes moved by one interface since the previous sample.
+#[derive(Debug, Clone)]
+pub struct InterfaceTraffic {
+    pub name: String,
+    pub received: u64,
+    pub transmitted: u64,
+}
+
+/// Traffic of every interface over the time since the previous sample.
+#[derive(Debug, Clone, Default)]
+pub struct NetworkSample {
+    pub elapsed: Duration,
+    pub interfaces: Vec<InterfaceTraffic>,
+    /// Interface of the IPv4 default route with the lowest metric.
+    pub default_route: Option<String>,
+}
+
+pub trait NetworkSource {
+    fn sample(&mut self) -> NetworkSample;
+}
+
+/// Operational state of a network interface.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkState {
+    Up,
+    /// Associating or authenticating, the kernel reports the link as dormant.
+    Connecting,
+    Down,
+}
+
+impl LinkState {
+    /// State for the contents of `/sys/class/net/<iface>/operstate`.
+    fn from_operstate(operstate: &str) -> Self {
+        match operstate.trim() {
+            // virtual links like tun devices never report up
+            "up" | "unknown" => LinkState::Up,
+            "dormant" | "testing" => LinkState::Connecting,
+            _ => LinkState::Down,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LinkStatus {
+    pub name: String,
+    pub state: LinkState,
+    /// Wi-Fi signal quality in percent, `None` for wired links.
+    pub signal: Option<f32>,
+}
+
+pub trait LinkSource {
+    /// State of every network interface.
+    fn links(&mut self) -> Vec<LinkStatus>;
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ChargeState {
+    Charging,
+    #[default]
+    Discharging,
+    Full,
+    /// Plugged in but held below full, by a charge limit for example.
+    NotCharging,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct BatteryStatus {
+    pub percent: f32,
+    pub state: ChargeState,
+    /// Charge or discharge rate in watts.
+    pub power: f32,
+    /// Full capacity in percent of the design capacity.
+    pub health: f32,
+    /// Time until empty while discharging, or until full while charging.
+    pub time_remaining: Option<Duration>,
+}
+
+pub trait BatterySource {
+    /// State of all batteries combined, `None` without one.
+    fn status(&mut self) -> Option<BatteryStatus>;
+}
+
+/// One temperature sensor of a hwmon chip.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TemperatureReading {
+    /// Driver name of the chip, like `k10temp` or `nvme`.
+    pub chip: String,
+    /// Sensor label, like `Tctl` or `Composite`, `temp<N>` without one.
+    pub label: String,
+    pub celsius: f32,
+}
+
+pub trait TemperatureSource {
+    fn temperatures(&mut self) -> Vec<TemperatureReading>;
+}
+
+/// One fan of a hwmon chip.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FanReading {
+    pub chip: String,
+    /// Fan label, `fan<N>` without one.
+    pub label: String,
+    pub rpm: f32,
+}
+
+pub trait FanSource {
+    fn fans(&mut self) -> Vec<FanReading>;
+}
+
+/// Wall clock time including the offset it is shown in.
+pub trait ClockSource {
+    fn now(&mut self) -> DateTime<FixedOffset>;
+}
+
+/// CPU usage reported by sysinfo.
+pub struct SystemCpu {
+    sys: System,
+    /// Highest frequency sysinfo reported, the maximum without cpufreq.
+    peak_mhz: f32,
+}
+
+impl SystemCpu {
+    pub fn new() -> Self {
+        let mut sys = System::new();
+        sys.refresh_cpu_all();
+        Self { sys, peak_mhz: 0.0 }
+    }
+}
+
+/// Current and maximum frequency of logical CPU `cpu` from cpufreq.
+fn read_cpufreq(cpu: usize) -> Option<CpuFrequency> {
+    let read_mhz = |file: &str| -> Option<f32> {
+        let path = format!("/sys/devices/system/cpu/cpu{cpu}/cpufreq/{file}");
+        let khz: f32 = std::fs::read_to_string(path).ok()?.trim().parse().ok()?;
+        Some(khz / 1000.0)
+    };
+    Some(CpuFrequency {
+        current: read_mhz("scaling_cur_freq")?,
+        max: read_mhz("cpuinfo_max_freq")?,
+    })
+}
+
+/// Read the location of logical CPU `cpu` from sysfs, the cache falls back
+/// to the package on machines without a shared L3.
+fn read_cpu_location(cpu: usize) -> Option<CpuLocation> {
+    let base = format!("/sys/devices/system/cpu/cpu{cpu}");
+    let read = |path: &str| -> Option<u32> {
+        std::fs::read_to_string(format!("{base}/{path}"))
+            .ok()?
+            .trim()
+            .parse()
+            .ok()
+    };
+
+    let package = read("topology/physical_package_id")?;
+    Some(CpuLocation {
+        package,
+        core: read("topology/core_id")?,
+        cache: read("cache/index3/id").unwrap_or(package),
+    })
+}
+
+impl CpuSource for SystemCpu {
+    fn usages(&mut self) -> Vec<f32> {
+        self.sys.refresh_cpu_all();
+        self.sys.cpus().iter().map(|cpu| cpu.cpu_usage()).collect()
+    }
+
+    fn topology(&mut self) -> Vec<CpuLocation> {
+        // without sysfs every logical CPU counts as its own core
+        (0..self.sys.cpus().len())
+            .map(|cpu| {
+                read_cpu_location(cpu).unwrap_or(CpuLocation {
+                    package: 0,
+                    core: cpu as u32,
+                    cache: 0,
+                })
+            })
+            .collect()
+    }
+
+    fn frequencies(&mut self) -> Vec<CpuFrequency> {
+        let count = self.sys.cpus().len();
+        if let Some(frequencies) = (0..count).map(read_cpufreq).collect() {
+            return frequencies;
+        }
+
+        // virtual machines often lack cpufreq, scale to the fastest reading
+        self.sys.refresh_cpu_frequency();
+        let current: Vec<f32> = self
+            .sys
+            .cpus()
+            .iter()
+            .map(|cpu| cpu.frequency() as f32)
+            .collect();
+        self.peak_mhz = current.iter().copied().fold(self.peak_mhz, f32::max);
+        current
+            .into_iter()
+            .map(|current| CpuFrequency {
+                current,
+                max: self.peak_mhz,
+            })
+            .collect()
+    }
+}
+
+/// Memory usage reported by sysinfo.
+pub struct SystemMemory {
+    sys: System,
+}
+
+impl SystemMemory {
+    pub fn new() -> Self {
+        Self { sys: System::new() }
+    }
+}
+
+/// Value of a `/proc/meminfo` field in bytes.
+fn meminfo_field(meminfo: &str, field: &str) -> Option<u64> {
+    meminfo.lines().find_map(|line| {
+        let value = line.strip_prefix(field)?.strip_prefix(':')?;
+        let kib = value
+            .trim()
+            .trim_end_matches("kB")
+            .trim()
+            .parse::<u64>()
+            .ok()?;
+        Some(kib * 1024)
+    })
+}
+
+/// Original and used size from the contents of a zram `mm_stat` file.
+fn parse_mm_stat(mm_stat: &str) -> Option<CompressionStats> {
+    let fields: Vec<u64> = mm_stat
+        .split_whitespace()
+        .map(|field| field.parse().ok())
+        .collect::<Option<_>>()?;
+    Some(CompressionStats {
+        original: *fields.first()?,
+        used: *fields.get(2)?,
+    })
+}
+
+/// Sum the stats of every zram device under `/sys/block`.
+fn read_zram() -> Option<CompressionStats> {
+    let mut total: Option<CompressionStats> = None;
+    for entry in std::fs::read_dir("/sys/block").ok()?.flatten() {
+        if !entry.file_name().to_string_lossy().starts_with("zram") {
+            continue;
+        }
+        let Some(stats) = std::fs::read_to_string(entry.path().join("mm_stat"))
+            .ok()
+            .and_then(|mm_stat| parse_mm_stat(&mm_stat))
+        else {
+            continue;
+        };
+        let sum = total.get_or_insert_with(CompressionStats::default);
+        sum.original += stats.original;
+        sum.used += stats.used;
+    }
+    total
+}
+
+const ZSWAP: &str = "/sys/kernel/debug/zswap";
+
+/// Size of the pages counted by `stored_pages`, Framework laptops are x86_64
+/// with 4 KiB pages.
+const PAGE_SIZE: u64 = 4096;
+
+/// Stored and pool size of zswap from its debugfs directory `root`, which is
+/// only readable by root and missing while zswap is disabled.
+fn read_zswap(root: &Path) -> Option<CompressionStats> {
+    let read = |file: &str| -> Option<u64> {
+        std::fs::read_to_string(root.join(file))
+            .ok()?
+            .trim()
+            .parse()
+            .ok()
+    };
+    Some(CompressionStats {
+        original: read("stored_pages")? * PAGE_SIZE,
+        used: read("pool_total_size")?,
+    })
+}
+
+impl MemorySource for SystemMemory {
+    fn memory(&mut self) -> MemoryStats {
+        self.sys.refresh_memory();
+        // sysinfo has no buffers and cache numbers
+        let meminfo = std::fs::read_to_string("/proc/meminfo").unwrap_or_default();
+        MemoryStats {
+            used: self.sys.used_memory(),
+            total: self.sys.total_memory(),
+            available: self.sys.available_memory(),
+            free: self.sys.free_memory(),
+            buffers: meminfo_field(&meminfo, "Buffers").unwrap_or(0),
+            cached: meminfo_field(&meminfo, "Cached").unwrap_or(0),
+            used_swap: self.sys.used_swap(),
+            total_swap: self.sys.total_swap(),
+            zram: read_zram(),
+            zswap: read_zswap(Path::new(ZSWAP)),
+        }
+    }
+}
+
+/// Interface counters reported by sysinfo.
+pub struct SystemNetworks {
+    networks: Networks,
+    last_sample: Instant,
+}
+
+impl SystemNetworks {
+    pub fn new() -> Self {
+        Self {
+            networks: Networks::new_with_refreshed_list(),
+            last_sample: Instant::now(),
+        }
+    }
+}
+
+/// Interface of the preferred default route in the contents of `/proc/net/route`.
+fn parse_default_route(route: &str) -> Option<String> {
+    const RTF_UP: u32 = 0x1;
+
+    route
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let (iface, destination, flags, metric) = (
+                fields.first()?,
+                fields.get(1)?,
+                fields.get(3)?,
+                fields.get(6)?,
+            );
+            let flags = u32::from_str_radix(flags, 16).ok()?;
+            if *destination != "00000000" || flags & RTF_UP == 0 {
+                return None;
+            }
+            Some((metric.parse::<u32>().ok()?, iface.to_string()))
+        })
+        .min()
+        .map(|(_, iface)| iface)
+}
+
+impl NetworkSource for SystemNetworks {
+    fn sample(&mut self) -> NetworkSample {
+        self.networks.refresh();
+        let elapsed = self.last_sample.elapsed();
+        self.last_sample = Instant::now();
+
+        NetworkSample {
+            elapsed,
+            interfaces: self
+                .networks
+                .iter()
+                .map(|(name, data)| InterfaceTraffic {
+                    name: name.clone(),
+                    received: data.received(),
+                    transmitted: data.transmitted(),
+                })
+                .collect(),
+            default_route: std::fs::read_to_string("/proc/net/route")
+                .ok()
+                .and_then(|route| parse_default_route(&route)),
+        }
+    }
+}
+
+/// Link quality of every wireless interface in `/proc/net/wireless`, in
+/// percent of the common maximum of 70.
+fn parse_wireless(wireless: &str) -> Vec<(String, f32)> {
+    wireless
+        .lines()
+        .skip(2)
+        .filter_map(|line| {
+            let (iface, stats) = line.split_once(':')?;
+            let link = stats.split_whitespace().nth(1)?.trim_end_matches('.');
+            let quality = link.parse::<f32>().ok()? / 70.0 * 100.0;
+            Some((iface.trim().to_string(), quality.clamp(0.0, 100.0)))
+        })
+        .collect()
+}
+
+/// Link states from `/sys/class/net` and Wi-Fi signal from `/proc/net/wireless`.
+pub struct SystemLinks;
+
+impl LinkSource for SystemLinks {
+    fn links(&mut self) -> Vec<LinkStatus> {
+        let wireless = std::fs::read_to_string("/proc/net/wireless")
+            .map(|wireless| parse_wireless(&wireless))
+            .unwrap_or_default();
+        let Ok(entries) = std::fs::read_dir("/sys/class/net") else {
+            return Vec::new();
+        };
+
+        entries
+            .flatten()
+            .map(|entry| {
+                let name = entry.file_name().to_string_lossy().into_owned();
+                let operstate =
+                    std::fs::read_to_string(entry.path().join("operstate")).unwrap_or_default();
+                let signal = wireless
+                    .iter()
+                    .find(|(iface, _)| *iface == name)
+                    .map(|&(_, quality)| quality);
+                LinkStatus {
+                    state: LinkState::from_operstate(&operstate),
+                    name,
+                    signal,
+                }
+            })
+            .collect()
+    }
+}
+
+/// One `<kind><N>_input` file of a hwmon chip with its label.
+#[derive(Debug, Clone, PartialEq)]
+struct HwmonInput {
+    chip: String,
+    label: String,
+    value: f32,
+}
+
+/// Every `<kind><N>_input` of the hwmon chips under `root`, `kind`
+/// being `temp`, `fan` and so on. Inputs without a label are named after
+/// their file, like `temp1`.
+fn read_hwmon(root: &Path, kind: &str) -> Vec<HwmonInput> {
+    let mut inputs = Vec::new();
+    let Ok(chips) = std::fs::read_dir(root) else {
+        return inputs;
+    };
+    let mut chips: Vec<PathBuf> = chips.flatten().map(|chip| chip.path()).collect();
+    chips.sort();
+
+    for path in chips {
+        let chip = std::fs::read_to_string(path.join("name"))
+            .map(|name| name.trim().to_string())
+            .unwrap_or_default();
+        let Ok(files) = std::fs::read_dir(&path) else {
+            continue;
+        };
+        let mut sensors: Vec<String> = files
+            .flatten()
+            .filter_map(|file| {
+                let name = file.file_name().to_string_lossy().into_owned();
+                let sensor = name.strip_suffix("_input")?;
+                let index = sensor.strip_prefix(kind)?;
+                index
+                    .chars()
+                    .all(|c| c.is_ascii_digit())
+                    .then(|| sensor.to_string())
+            })
+            .collect();
+        sensors.sort();
+
+        for sensor in sensors {
+            let Some(value) = std::fs::read_to_string(path.join(format!("{sensor}_input")))
+                .ok()
+                .and_then(|value| value.trim().parse::<f32>().ok())
+            else {
+                continue;
+            };
+            let label = std::fs::read_to_string(path.join(format!("{sensor}_label")))
+                .map(|label| label.trim().to_string())
+                .unwrap_or(sensor);
+            inputs.push(HwmonInput {
+                chip: chip.clone(),
+                label,
+                value,
+            });
+        }
+    }
+    inputs
+}
+
+/// Temperatures of the hwmon chips under a directory, `/sys/class/hwmon` on
+/// a real system.
+pub struct SystemTemperatures {
+    root: PathBuf,
+}
+
+impl SystemTemperatures {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+}
+
+impl TemperatureSource for SystemTemperatures {
+    fn temperatures(&mut self) -> Vec<TemperatureReading> {
+        read_hwmon(&self.root, "temp")
+            .into_iter()
+            .map(|input| TemperatureReading {
+                chip: input.chip,
+                label: input.label,
+                // hwmon reports millidegrees
+                celsius: input.value / 1000.0,
+            })
+            .collect()
+    }
+}
+
+/// Fans of the hwmon chips under a directory, `/sys/class/hwmon` on a real
+/// system.
+pub struct SystemFans {
+    root: PathBuf,
+}
+
+impl SystemFans {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+}
+
+impl FanSource for SystemFans {
+    fn fans(&mut self) -> Vec<FanReading> {
+        read_hwmon(&self.root, "fan")
+            .into_iter()
+            .map(|input| FanReading {
+                chip: input.chip,
+                label: input.label,
+                rpm: input.value,
+            })
+            .collect()
+    }
+}
+
+/// Charge of one battery in watt hours and its rate in watts.
+#[derive(Debug, Clone, Copy)]
+struct BatteryEnergy {
+    energy: f32,
+    full: f32,
+    full_design: f32,
+    rate: f32,
+    state: ChargeState,
+}
+
+/// Combine batteries as if they were one, `None` without any.
+fn combine_batteries(batteries: &[BatteryEnergy]) -> Option<BatteryStatus> {
+    if batteries.is_empty() {
+        return None;
+    }
+    let energy: f32 = batteries.iter().map(|battery| battery.energy).sum();
+    let full: f32 = batteries.iter().map(|battery| battery.full).sum();
+    let full_design: f32 = batteries.iter().map(|battery| battery.full_design).sum();
+    let power: f32 = batteries.iter().map(|battery| battery.rate).sum();
+    let has = |state| batteries.iter().any(|battery| battery.state == state);
+
+    let state = if has(ChargeState::Charging) {
+        ChargeState::Charging
+    } else if has(ChargeState::Discharging) {
+        ChargeState::Discharging
+    } else if batteries
+        .iter()
+        .all(|battery| battery.state == ChargeState::Full)
+    {
+        ChargeState::Full
+    } else {
+        ChargeState::NotCharging
+    };
+    let hours = match state {
+        ChargeState::Charging => (full - energy) / power,
+        ChargeState::Discharging => energy / power,
+        _ => f32::NAN,
+    };
+
+    Some(BatteryStatus {
+        percent: if full > 0.0 {
+            energy / full * 100.0
+        } else {
+            0.0
+        },
+        state,
+        power,
+        health: if full_design > 0.0 {
+            full / full_design * 100.0
+        } else {
+            100.0
+        },
+        time_remaining: (hours.is_finite() && hours >= 0.0)
+            .then(|| Duration::from_secs_f32(hours * 3600.0)),
+    })
+}
+
+/// Battery state reported by the `battery` crate.
+pub struct SystemBattery;
+
+impl BatterySource for SystemBattery {
+    fn status(&mut self) -> Option<BatteryStatus> {
+        use battery::units::{energy::watt_hour, power::watt};
+
+        let batteries: Vec<BatteryEnergy> = battery::Manager::new()
+            .ok()?
+            .batteries()
+            .ok()?
+            .flatten()
+            .map(|battery| BatteryEnergy {
+                energy: battery.energy().get::<watt_hour>(),
+                full: battery.energy_full().get::<watt_hour>(),
+                full_design: battery.energy_full_design().get::<watt_hour>(),
+                rate: battery.energy_rate().get::<watt>(),
+                state: match battery.state() {
+                    battery::State::Charging => ChargeState::Charging,
+                    battery::State::Discharging | battery::State::Empty => ChargeState::Discharging,
+                    battery::State::Full => ChargeState::Full,
+                    // the kernel reports "Not charging" as unknown
+                    _ => ChargeState::NotCharging,
+                },
+            })
+            .collect();
+        combine_batteries(&batteries)
+    }
+}
+
+/// The local system time.
+pub struct SystemClock;
+
+impl ClockSource for SystemClock {
+    fn now(&mut self) -> DateTime<FixedOffset> {
+        Local::now().fixed_offset()
+    }
+}
+
+/// The time of another source moved into a fixed time zone.
+pub struct ZonedClock {
+    zone: Tz,
+    clock: Box<dyn ClockSource>,
+}
+
+impl ZonedClock {
+    pub fn new(zone: Tz, clock: Box<dyn ClockSource>) -> Self {
+        Self { zone, clock }
+    }
+}
+
+impl ClockSource for ZonedClock {
+    fn now(&mut self) -> DateTime<FixedOffset> {
+        self.clock.now().with_timezone(&self.zone).fixed_offset()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::widget::snapshot::TempDir;
+
+    #[test]
+    fn meminfo_fields_in_bytes() {
+        let meminfo = "MemTotal:       32768000 kB\nBuffers:           73252 kB\nCached:          3682180 kB\nSwapCached:            0 kB\n";
+        assert_eq!(meminfo_field(meminfo, "Buffers"), Some(73252 * 1024));
+        assert_eq!(meminfo_field(meminfo, "Cached"), Some(3682180 * 1024));
+        assert_eq!(meminfo_field(meminfo, "Dirty"), None);
+    }
+
+    #[test]
+    fn zram_mm_stat() {
+        let stats = parse_mm_stat(
+            "  4096000  1024000  1365333        0  1400000      512       0      12      3\n",
+        )
+        .unwrap();
+        assert_eq!(stats.original, 4096000);
+        assert_eq!(stats.used, 1365333);
+        assert!((stats.ratio() - 3.0).abs() < 0.01);
+        assert_eq!(parse_mm_stat("garbage"), None);
+        assert_eq!(CompressionStats::default().ratio(), 1.0);
+    }
+
+    #[test]
+    fn zswap_from_debugfs() {
+        let root = TempDir::new("zswap-test");
+        assert_eq!(read_zswap(root.path()), None);
+
+        std::fs::write(root.path().join("stored_pages"), "3072\n").unwrap();
+        assert_eq!(read_zswap(root.path()), None);
+        std::fs::write(root.path().join("pool_total_size"), "4194304\n").unwrap();
+        let stats = read_zswap(root.path()).unwrap();
+        assert_eq!(stats.original, 3072 * 4096);
+        assert_eq!(stats.ratio(), 3.0);
+    }
+
+    #[test]
+    fn default_route_prefers_lowest_metric() {
+        let route =
+            "Iface\tDestination\tGateway \tFlags\tRefCnt\tUse\tMetric\tMask\t\tMTU\tWindow\tIRTT\n\
+            wlan0\t00000000\t0102A8C0\t0003\t0\t0\t600\t00000000\t0\t0\t0\n\
+            enp5s0\t00000000\t0100A8C0\t0003\t0\t0\t100\t00000000\t0\t0\t0\n\
+            enp5s0\t0000A8C0\t00000000\t0001\t0\t0\t100\t00FFFFFF\t0\t0\t0\n\
+            tun0\t00000000\t00000000\t0000\t0\t0\t0\t00000000\t0\t0\t0\n";
+        assert_eq!(parse_default_route(route).as_deref(), Some("enp5s0"));
+        assert_eq!(parse_default_route("Iface\tDestination\n"), None);
+    }
+
+    #[test]
+    fn wireless_link_quality() {
+        let wireless =
+            "Inter-| sta-|   Quality        |   Discarded packets               | Missed | WE\n \
+            face | tus | link level noise |  nwid  crypt   frag  retry   misc | beacon | 22\n \
+            wlan0: 0000   56.  -54.  -256        0      0      0      0     21        0\n";
+        assert_eq!(parse_wireless(wireless), vec![("wlan0".to_string(), 80.0)]);
+        assert_eq!(
+            LinkState::from_operstate("dormant\n"),
+            LinkState::Connecting
+        );
+        assert_eq!(
+            LinkState::from_operstate("lowerlayerdown\n"),
+            LinkState::Down
+        );
+    }
+
+    #[test]
+    fn hwmon_inputs_with_labels() {
+        let root = TempDir::hwmon(
+            "hwmon-test",
+            &[(
+                "k10temp",
+                &[
+                    ("temp1_input", "61250"),
+                    ("temp1_label", "Tctl"),
+                    ("temp3_input", "48000"),
+                    ("temp1_crit", "95000"),
+                    ("fan1_input", "1200"),
+                ],
+            )],
+        );
+
+        let inputs = read_hwmon(root.path(), "temp");
+        assert_eq!(
+            inputs,
+            vec![
+                HwmonInput {
+                    chip: "k10temp".to_string(),
+                    label: "Tctl".to_string(),
+                    value: 61250.0,
+                },
+                HwmonInput {
+                    chip: "k10temp".to_string(),
+                    label: "temp3".to_string(),
+                    value: 48000.0,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn batteries_combine_into_one() {
+        let battery = |energy, full, rate, state| BatteryEnergy {
+            energy,
+            full,
+            full_design: 60.0,
+            rate,
+            state,
+        };
+        let status = combine_batteries(&[
+            battery(30.0, 50.0, 6.0, ChargeState::Discharging),
+            battery(10.0, 30.0, 4.0, ChargeState::NotCharging),
+        ])
+        .unwrap();
+        assert_eq!(status.percent, 50.0);
+        assert_eq!(status.state, ChargeState::Discharging);
+        assert_eq!(status.power, 10.0);
+        assert!((status.health - 66.67).abs() < 0.01);
+        assert_eq!(status.time_remaining, Some(Duration::from_secs(4 * 3600)));
+
+        let charging = combine_batteries(&[battery(40.0, 50.0, 20.0, ChargeState::Charging)]);
+        assert_eq!(
+            charging.unwrap().time_remaining,
+            Some(Duration::from_secs(30 * 60))
+        );
+        let full = combine_batteries(&[battery(50.0, 50.0, 0.0, ChargeState::Full)]).unwrap();
+        assert_eq!(full.time_remaining, None);
+        assert!(combine_batteries(&[]).is_none());
+    }
+}
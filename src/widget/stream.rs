@@ -0,0 +1,384 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{self, BufRead, BufReader, Read},
+    os::unix::fs::FileTypeExt,
+    sync::{Arc, Mutex, OnceLock},
+    thread,
+    time::Duration,
+};
+
+use crate::config::{StreamFormat, WidgetStreamSetup};
+
+use super::{Shape, Widget, OFF, ON_FULL};
+
+/// Reader state shared between a source's thread and its widgets.
+struct Source {
+    /// Format and size the next frame is read with.
+    settings: (StreamFormat, usize, usize),
+    /// Latest complete frame.
+    frame: Option<Vec<u8>>,
+}
+
+type SharedSource = Arc<Mutex<Source>>;
+
+/// First wait before opening a missing source again, doubled up to
+/// `MAX_RETRY_DELAY` while it keeps failing.
+const RETRY_DELAY: Duration = Duration::from_millis(250);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(30);
+
+/// One reader per source, a reload or layout switch reuses it so stdin is
+/// never read by two threads.
+fn sources() -> &'static Mutex<HashMap<String, SharedSource>> {
+    static SOURCES: OnceLock<Mutex<HashMap<String, SharedSource>>> = OnceLock::new();
+    SOURCES.get_or_init(Default::default)
+}
+
+/// Shows frames an external process writes to stdin or a named pipe.
+pub struct StreamWidget {
+    source: SharedSource,
+    matrix: Vec<u8>,
+    shape: Shape,
+}
+
+impl StreamWidget {
+    pub fn new(setup: &WidgetStreamSetup) -> Self {
+        let settings = (setup.format, setup.width, setup.height);
+        let mut sources = sources().lock().unwrap_or_else(|err| err.into_inner());
+        let source = sources
+            .entry(setup.source.clone())
+            .or_insert_with(|| {
+                let source = Arc::new(Mutex::new(Source {
+                    settings,
+                    frame: None,
+                }));
+                let path = setup.source.clone();
+                let shared = Arc::clone(&source);
+                thread::spawn(move || read_source(&path, &shared));
+                source
+            })
+            .clone();
+        source
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .settings = settings;
+
+        Self {
+            source,
+            matrix: vec![OFF; setup.width * setup.height],
+            shape: Shape {
+                x: setup.width,
+                y: setup.height,
+            },
+        }
+    }
+}
+
+impl Widget for StreamWidget {
+    fn update(&mut self) {
+        let source = self.source.lock().unwrap_or_else(|err| err.into_inner());
+        // frames read before a resize don't fit anymore
+        if let Some(frame) = source
+            .frame
+            .as_ref()
+            .filter(|f| f.len() == self.matrix.len())
+        {
+            self.matrix.copy_from_slice(frame);
+        }
+    }
+
+    fn get_matrix(&self) -> &[u8] {
+        &self.matrix
+    }
+
+    fn get_shape(&self) -> &Shape {
+        &self.shape
+    }
+}
+
+fn read_source(path: &str, source: &Mutex<Source>) {
+    let mut retry_delay = RETRY_DELAY;
+    loop {
+        let (reader, reopen): (Box<dyn Read>, bool) = if path == "-" {
+            (Box::new(io::stdin()), false)
+        } else {
+            // opening a named pipe blocks until a writer shows up
+            match File::open(path) {
+                Ok(file) => {
+                    retry_delay = RETRY_DELAY;
+                    let is_fifo = file.metadata().is_ok_and(|meta| meta.file_type().is_fifo());
+                    (Box::new(file), is_fifo)
+                }
+                Err(err) => {
+                    // the writer may create the pipe after the daemon started
+                    if retry_delay == RETRY_DELAY {
+                        log::warn!("unable to open stream source {path}, retrying: {err}");
+                    }
+                    thread::sleep(retry_delay);
+                    retry_delay = (retry_delay * 2).min(MAX_RETRY_DELAY);
+                    continue;
+                }
+            }
+        };
+
+        let mut reader = BufReader::new(reader);
+        loop {
+            let (format, width, height) = source
+                .lock()
+                .unwrap_or_else(|err| err.into_inner())
+                .settings;
+            match read_frame(&mut reader, format, width, height) {
+                Ok(Some(frame)) => {
+                    source.lock().unwrap_or_else(|err| err.into_inner()).frame = Some(frame);
+                }
+                Ok(None) => break,
+                Err(err) => {
+                    log::warn!("dropping stream {path}: {err}");
+                    break;
+                }
+            }
+        }
+
+        // a pipe is reopened for the next writer, stdin and files just end
+        if !reopen {
+            log::info!("stream source {path} ended");
+            return;
+        }
+    }
+}
+
+/// Read one frame of `width * height` levels, `None` at the end of input.
+fn read_frame(
+    reader: &mut impl BufRead,
+    format: StreamFormat,
+    width: usize,
+    height: usize,
+) -> Result<Option<Vec<u8>>, String> {
+    let count = width * height;
+    match format {
+        StreamFormat::Raw => read_exact(reader, count),
+        StreamFormat::Packed => Ok(read_exact(reader, count.div_ceil(8))?.map(|bytes| {
+            (0..count)
+                .map(|idx| {
+                    if (bytes[idx / 8] >> (idx % 8)) & 1 == 1 {
+                        ON_FULL
+                    } else {
+                        OFF
+                    }
+                })
+                .collect()
+        })),
+        StreamFormat::Pgm => read_pgm(reader, width, height),
+    }
+}
+
+fn read_exact(reader: &mut impl Read, len: usize) -> Result<Option<Vec<u8>>, String> {
+    let mut buf = vec![0; len];
+    match reader.read_exact(&mut buf) {
+        Ok(()) => Ok(Some(buf)),
+        Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => Ok(None),
+        Err(err) => Err(err.to_string()),
+    }
+}
+
+/// Next whitespace separated header token, skipping `#` comments.
+fn pgm_token(reader: &mut impl BufRead) -> Result<Option<String>, String> {
+    let mut token = String::new();
+    let mut comment = false;
+    loop {
+        let mut byte = [0];
+        if reader.read(&mut byte).map_err(|err| err.to_string())? == 0 {
+            return Ok((!token.is_empty()).then_some(token));
+        }
+        let byte = byte[0] as char;
+        if comment {
+            comment = byte != '\n';
+        } else if byte == '#' {
+            comment = true;
+        } else if byte.is_ascii_whitespace() {
+            if !token.is_empty() {
+                return Ok(Some(token));
+            }
+        } else {
+            token.push(byte);
+        }
+    }
+}
+
+fn read_pgm(
+    reader: &mut impl BufRead,
+    width: usize,
+    height: usize,
+) -> Result<Option<Vec<u8>>, String> {
+    let Some(magic) = pgm_token(reader)? else {
+        return Ok(None);
+    };
+    if magic != "P5" {
+        return Err(format!("expected a binary PGM (P5), got \"{magic}\""));
+    }
+
+    let mut header = [0; 3];
+    for value in header.iter_mut() {
+        *value = pgm_token(reader)?
+            .and_then(|token| token.parse::<usize>().ok())
+            .ok_or_else(|| "truncated PGM header".to_string())?;
+    }
+    let [image_width, image_height, max] = header;
+    if (image_width, image_height) != (width, height) {
+        return Err(format!(
+            "PGM is {image_width}x{image_height}, the widget is {width}x{height}"
+        ));
+    }
+    if max == 0 || max > u16::MAX as usize {
+        return Err(format!("invalid PGM maximum {max}"));
+    }
+
+    // samples wider than a byte are big-endian
+    let sample_size = if max > 255 { 2 } else { 1 };
+    let Some(data) = read_exact(reader, width * height * sample_size)? else {
+        return Ok(None);
+    };
+    Ok(Some(
+        data.chunks(sample_size)
+            .map(|sample| {
+                let value = sample.iter().fold(0, |acc, &b| (acc << 8) | b as usize);
+                (value.min(max) * 255 / max) as u8
+            })
+            .collect(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use super::*;
+    use crate::matrix::{self, MATRIX_HEIGHT, MATRIX_WIDTH};
+
+    fn frame(
+        format: StreamFormat,
+        width: usize,
+        height: usize,
+        bytes: &[u8],
+    ) -> Result<Option<Vec<u8>>, String> {
+        read_frame(&mut io::Cursor::new(bytes), format, width, height)
+    }
+
+    #[test]
+    fn packed_bits_match_the_serial_encoding() {
+        let mut lit = [[false; MATRIX_WIDTH]; MATRIX_HEIGHT];
+        for (y, row) in lit.iter_mut().enumerate() {
+            for (x, led) in row.iter_mut().enumerate() {
+                *led = (x * 7 + y * 3) % 5 < 2;
+            }
+        }
+        let expected: Vec<u8> = lit
+            .iter()
+            .flatten()
+            .map(|&on| if on { ON_FULL } else { OFF })
+            .collect();
+
+        let packed = matrix::encode(lit);
+        assert_eq!(
+            frame(StreamFormat::Packed, MATRIX_WIDTH, MATRIX_HEIGHT, &packed),
+            Ok(Some(expected))
+        );
+        // the first pixel is the lowest bit
+        assert_eq!(
+            frame(StreamFormat::Packed, 3, 3, &[0b0000_0101, 0b1]),
+            Ok(Some(vec![
+                ON_FULL, OFF, ON_FULL, OFF, OFF, OFF, OFF, OFF, ON_FULL
+            ]))
+        );
+    }
+
+    #[test]
+    fn pgm_header_with_comments() {
+        let mut bytes = b"P5\n# made by hand\n3 # width\n1\n# depth next\n255\n".to_vec();
+        bytes.extend([0, 128, 255]);
+        assert_eq!(
+            frame(StreamFormat::Pgm, 3, 1, &bytes),
+            Ok(Some(vec![0, 128, 255]))
+        );
+    }
+
+    #[test]
+    fn pgm_16_bit_samples() {
+        let mut bytes = b"P5 2 1 1023\n".to_vec();
+        // big-endian, values above the maximum are clamped
+        bytes.extend([0x03, 0xff, 0xff, 0xff]);
+        assert_eq!(
+            frame(StreamFormat::Pgm, 2, 1, &bytes),
+            Ok(Some(vec![255, 255]))
+        );
+        let mut bytes = b"P5 2 1 1020\n".to_vec();
+        bytes.extend([0x01, 0xfe, 0x00, 0x00]);
+        assert_eq!(
+            frame(StreamFormat::Pgm, 2, 1, &bytes),
+            Ok(Some(vec![127, 0]))
+        );
+    }
+
+    #[test]
+    fn short_frames_wait_for_more_data() {
+        assert_eq!(frame(StreamFormat::Raw, 3, 2, &[1, 2, 3]), Ok(None));
+        assert_eq!(frame(StreamFormat::Packed, 3, 3, &[0xff]), Ok(None));
+        assert_eq!(
+            frame(StreamFormat::Pgm, 2, 1, b"P5 2 1 255\n\x01"),
+            Ok(None)
+        );
+        assert_eq!(frame(StreamFormat::Pgm, 2, 1, b""), Ok(None));
+        assert!(frame(StreamFormat::Pgm, 2, 1, b"P5 2").is_err());
+    }
+
+    #[test]
+    fn oversized_frames_are_split_or_rejected() {
+        // raw data longer than a frame is the start of the next one
+        let mut reader = io::Cursor::new(vec![1, 2, 3, 4, 5]);
+        assert_eq!(
+            read_frame(&mut reader, StreamFormat::Raw, 2, 1),
+            Ok(Some(vec![1, 2]))
+        );
+        assert_eq!(
+            read_frame(&mut reader, StreamFormat::Raw, 2, 1),
+            Ok(Some(vec![3, 4]))
+        );
+        assert_eq!(read_frame(&mut reader, StreamFormat::Raw, 2, 1), Ok(None));
+
+        // a PGM names its size, a different one is an error
+        let mut bytes = b"P5 3 1 255\n".to_vec();
+        bytes.extend([1, 2, 3]);
+        assert_eq!(
+            frame(StreamFormat::Pgm, 2, 1, &bytes),
+            Err("PGM is 3x1, the widget is 2x1".to_string())
+        );
+        assert!(frame(StreamFormat::Pgm, 2, 1, b"P2 2 1 255\n1 2").is_err());
+        assert!(frame(StreamFormat::Pgm, 2, 1, b"P5 2 1 0\n\x01\x02").is_err());
+    }
+
+    #[test]
+    fn missing_source_is_opened_once_it_appears() {
+        let path = std::env::temp_dir().join(format!("stream-test-{}", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let setup = WidgetStreamSetup {
+            source: path.to_string_lossy().into_owned(),
+            format: StreamFormat::Raw,
+            width: 2,
+            height: 1,
+        };
+        let mut widget = StreamWidget::new(&setup);
+        thread::sleep(RETRY_DELAY / 2);
+        // renamed into place so the reader never sees an empty file
+        let partial = path.with_extension("partial");
+        std::fs::write(&partial, [ON_FULL, 7]).unwrap();
+        std::fs::rename(&partial, &path).unwrap();
+
+        let start = Instant::now();
+        while widget.get_matrix() != [ON_FULL, 7] && start.elapsed() < Duration::from_secs(5) {
+            thread::sleep(Duration::from_millis(20));
+            widget.update();
+        }
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(widget.get_matrix(), [ON_FULL, 7]);
+    }
+}
@@ -0,0 +1,145 @@
+use crate::config::{TemperatureStyle, WidgetTemperatureSetup};
+
+use super::{
+    font::{self, GLYPH_HEIGHT},
+    sensor_matches,
+    source::{TemperatureReading, TemperatureSource},
+    write_bar_1l, Shape, Widget, OFF, ON_FULL,
+};
+
+/// Create a widget that displays temperature sensors as bars or numbers,
+/// blinking while a sensor is at the critical threshold.
+pub struct TemperatureWidget {
+    source: Box<dyn TemperatureSource>,
+    setup: WidgetTemperatureSetup,
+    /// Update counter driving the critical blink.
+    phase: usize,
+    matrix: Vec<u8>,
+    shape: Shape,
+}
+
+impl TemperatureWidget {
+    pub fn new(setup: &WidgetTemperatureSetup, source: Box<dyn TemperatureSource>) -> Self {
+        let rows = match setup.style {
+            TemperatureStyle::Bars => setup.sensors.len(),
+            // numbers are separated by an empty row
+            TemperatureStyle::Numerals => setup.sensors.len() * (GLYPH_HEIGHT + 1) - 1,
+        };
+        Self {
+            source,
+            setup: setup.clone(),
+            phase: 0,
+            matrix: Vec::new(),
+            shape: Shape { x: 9, y: rows },
+        }
+    }
+
+    /// Hottest reading matching `pattern`.
+    fn hottest(readings: &[TemperatureReading], pattern: &str) -> Option<f32> {
+        readings
+            .iter()
+            .filter(|reading| sensor_matches(pattern, &reading.chip, &reading.label))
+            .map(|reading| reading.celsius)
+            .reduce(f32::max)
+    }
+}
+
+impl Widget for TemperatureWidget {
+    fn update(&mut self) {
+        let readings = self.source.temperatures();
+
+        // recreate matrix
+        let width = self.get_shape().x;
+        let height = self.get_shape().y;
+        self.matrix = vec![OFF; width * height];
+
+        let blink_off = !self.phase.is_multiple_of(2);
+        self.phase = self.phase.wrapping_add(1);
+
+        for (idx, pattern) in self.setup.sensors.iter().enumerate() {
+            // sensors that are missing stay dark
+            let Some(celsius) = Self::hottest(&readings, pattern) else {
+                continue;
+            };
+            let critical = self.setup.critical.is_some_and(|limit| celsius >= limit);
+            if critical && blink_off {
+                continue;
+            }
+
+            match self.setup.style {
+                TemperatureStyle::Bars => write_bar_1l(
+                    &mut self.matrix,
+                    idx * width,
+                    width,
+                    celsius - self.setup.min,
+                    self.setup.max - self.setup.min,
+                ),
+                TemperatureStyle::Numerals => {
+                    let text = format!("{:02}", celsius.clamp(0.0, 99.0).round() as u32);
+                    let columns = font::text_columns(&text);
+                    font::draw_columns(
+                        &mut self.matrix,
+                        width,
+                        1,
+                        idx * (GLYPH_HEIGHT + 1),
+                        columns.len(),
+                        &columns,
+                        0,
+                        ON_FULL,
+                    );
+                }
+            }
+        }
+    }
+
+    fn get_matrix(&self) -> &[u8] {
+        &self.matrix
+    }
+
+    fn get_shape(&self) -> &Shape {
+        &self.shape
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::widget::snapshot::{assert_snapshot, FixedTemperatures};
+
+    fn widget(style: TemperatureStyle) -> TemperatureWidget {
+        let setup = WidgetTemperatureSetup {
+            sensors: vec![
+                "Tctl".to_string(),
+                "amdgpu:edge".to_string(),
+                "nvme:*".to_string(),
+            ],
+            style,
+            min: 30.0,
+            max: 100.0,
+            critical: Some(90.0),
+        };
+        let readings = vec![
+            ("k10temp", "Tctl", 92.4),
+            ("amdgpu", "edge", 47.0),
+            ("nvme", "Composite", 38.0),
+            ("nvme", "Sensor 1", 51.5),
+        ];
+        TemperatureWidget::new(&setup, Box::new(FixedTemperatures(readings)))
+    }
+
+    #[test]
+    fn bars_blink_when_critical() {
+        let mut widget = widget(TemperatureStyle::Bars);
+        widget.update();
+        assert_snapshot("temperature_bars", &widget);
+        widget.update();
+        assert_snapshot("temperature_bars_blink", &widget);
+    }
+
+    #[test]
+    fn numerals() {
+        let mut widget = widget(TemperatureStyle::Numerals);
+        widget.update();
+        assert_snapshot("temperature_numerals", &widget);
+    }
+}